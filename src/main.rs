@@ -1,13 +1,30 @@
 #[macro_use]
 extern crate clap;
 
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::convert::TryFrom;
+use std::fmt;
 use std::fs;
 
-#[derive(Debug)]
+use quote::ToTokens;
+use sha2::{Digest, Sha256};
+
+#[derive(Clone, Debug)]
 struct SimpleType {
     path: Vec<String>,
     // Generic args are only allowed in the final segment
     generic_args: Vec<SimpleType>,
+    // Set for fixed-size arrays, either a number or the name of a
+    // constant. The path is empty and the element type is the only
+    // generic arg.
+    array_len: Option<String>,
+    // Const generic arguments like the 3 in `Matrix<f64, 3>` or the
+    // `CAP + 1` in `ArrayVec<u8, { CAP + 1 }>`. They don't appear in
+    // the TypeScript type.
+    const_args: Vec<String>,
+    // Set for trait objects like `dyn Error + Send`. The path is the
+    // main trait's path.
+    trait_object: bool,
 }
 
 #[derive(Debug)]
@@ -17,56 +34,1000 @@ enum SimpleTypeError {
     EarlyGenericArgs,
     InvalidGenericArgType,
     InvalidArgType,
+    InvalidArrayLen,
+    ImplTrait,
+    FnPointer,
+    RawPointer,
     TypeIsNotPath,
 }
 
-#[derive(Debug)]
+impl fmt::Display for SimpleTypeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match self {
+            SimpleTypeError::QSelf => {
+                "qualified self types like `<T as Trait>::X` are not supported"
+            }
+            SimpleTypeError::LeadingColon => "paths with a leading `::` are not supported",
+            SimpleTypeError::EarlyGenericArgs => {
+                "generic arguments are only supported in the last path segment"
+            }
+            SimpleTypeError::InvalidGenericArgType => "unsupported generic argument",
+            SimpleTypeError::InvalidArgType => "unsupported path arguments, e.g. `Fn(A) -> B`",
+            SimpleTypeError::InvalidArrayLen => "array length must be a number or a constant",
+            SimpleTypeError::ImplTrait => "`impl Trait` has no concrete type to convert",
+            SimpleTypeError::FnPointer => "function pointers can't be serialized",
+            SimpleTypeError::RawPointer => "raw pointers can't be serialized",
+            SimpleTypeError::TypeIsNotPath => "unsupported type",
+        };
+        f.write_str(msg)
+    }
+}
+
+#[derive(Clone, Debug)]
 struct SimpleField {
     name: Option<String>,
     ty: SimpleType,
+    // From #[serde(rename = "...")], takes precedence over the
+    // struct's rename_all
+    rename: Option<String>,
+    // From #[serde(skip_serializing)], #[serde(skip_deserializing)],
+    // or #[serde(skip)] for both
+    skip_serializing: bool,
+    skip_deserializing: bool,
+    // From #[serde(skip_serializing_if = "...")]
+    skip_serializing_if: bool,
+    // From #[serde(default)] on the field or the struct
+    default: bool,
+    // From #[serde(flatten)]
+    flatten: bool,
+    // From #[serde(alias = "...")]
+    aliases: Vec<String>,
+    // Module path from #[serde(with = "...")]
+    with: Option<String>,
+    // Uses #[serde(serialize_with)] or #[serde(deserialize_with)]
+    custom_serde: bool,
+    // From #[rsts(type = "...")], overrides the generated type
+    type_override: Option<String>,
+    // Lines of the doc comment
+    doc: Vec<String>,
 }
 
 impl SimpleField {
+    fn from_syn_field(
+        field: &syn::Field,
+        default_all: bool,
+    ) -> Result<SimpleField, SimpleTypeError> {
+        let name = field.ident.as_ref().map(ident_name);
+        let mut sf = SimpleField::new(name, SimpleType::from_syn_type(&field.ty)?);
+        sf.rename = serde_str(&field.attrs, "rename");
+        let skip = serde_flag(&field.attrs, "skip");
+        sf.skip_serializing = skip || serde_flag(&field.attrs, "skip_serializing");
+        sf.skip_deserializing = skip || serde_flag(&field.attrs, "skip_deserializing");
+        sf.skip_serializing_if = serde_str(&field.attrs, "skip_serializing_if").is_some();
+        sf.default = default_all || serde_default(&field.attrs);
+        sf.flatten = serde_flag(&field.attrs, "flatten");
+        sf.aliases = serde_strs(&field.attrs, "alias");
+        sf.with = serde_str(&field.attrs, "with");
+        sf.custom_serde = serde_str(&field.attrs, "serialize_with").is_some()
+            || serde_str(&field.attrs, "deserialize_with").is_some();
+        sf.type_override = attr_strs(&field.attrs, "rsts", "type").into_iter().next();
+        sf.doc = doc_lines(&field.attrs);
+        Ok(sf)
+    }
+
     fn new(name: Option<String>, ty: SimpleType) -> SimpleField {
-        SimpleField { name, ty }
+        SimpleField {
+            name,
+            ty,
+            rename: None,
+            skip_serializing: false,
+            skip_deserializing: false,
+            skip_serializing_if: false,
+            default: false,
+            flatten: false,
+            aliases: Vec::new(),
+            with: None,
+            custom_serde: false,
+            type_override: None,
+            doc: Vec::new(),
+        }
+    }
+
+    /// Check if the field might be missing from the JSON
+    fn is_optional(&self, opts: &Options) -> bool {
+        // A field skipped in only one direction might be missing
+        // when the type is used for both
+        (opts.direction == Direction::Both && (self.skip_serializing || self.skip_deserializing))
+            || (opts.direction != Direction::Deserialize && self.skip_serializing_if)
+            || (opts.direction != Direction::Serialize && self.default)
+            // The "absent vs null" pattern: the outer None is a
+            // missing field and the inner None is null
+            || (self.ty.is_option() && self.ty.generic_args[0].is_option())
+    }
+
+    /// Get the type set with `#[rsts(type)]` or configured for
+    /// `#[serde(with)]`, or None if the Rust type should be used.
+    /// Warns about anything that needs configuring; `place` names
+    /// the field in the warnings.
+    fn configured_type(&self, place: &str, opts: &Options) -> Option<String> {
+        if let Some(ty) = &self.type_override {
+            return Some(ty.to_string());
+        }
+        let with_type = self.with.as_ref().and_then(|w| {
+            opts.with_types
+                .get(w)
+                .cloned()
+                .or_else(|| known_with_type(w))
+                .or_else(|| serde_bytes_type(w, &self.ty, opts))
+        });
+        if with_type.is_some() {
+            return with_type;
+        }
+        for name in self.ty.unmapped_traits(opts) {
+            eprintln!(
+                "{}: no type configured for `dyn {}`, see --trait-type",
+                place, name
+            );
+        }
+        if let Some(with) = &self.with {
+            eprintln!(
+                "{}: no type configured for `with = \"{}\"`, see --with-type",
+                place, with
+            );
+        } else if self.custom_serde {
+            eprintln!(
+                "{}: custom serialization, the type can be set with \
+                 #[rsts(type = \"...\")]",
+                place
+            );
+        }
+        None
+    }
+
+    /// Get the TypeScript type of an unnamed field
+    fn to_ts(&self, place: &str, opts: &Options) -> String {
+        self.configured_type(place, opts)
+            .unwrap_or_else(|| self.ty.to_ts(opts))
+    }
+
+    /// Get the TypeScript type of an object member, which leaves
+    /// out the `| null` of an optional field with --collapse-option
+    fn member_to_ts(&self, place: &str, opts: &Options) -> String {
+        match self.configured_type(place, opts) {
+            Some(ty) => ty,
+            None if opts.collapse_option && self.ty.is_option() && self.is_optional(opts) => {
+                self.ty.generic_args[0].to_ts(opts)
+            }
+            None => self.ty.to_ts(opts),
+        }
+    }
+}
+
+/// Which side of the JSON the generated types describe
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Direction {
+    // Types received from Rust, i.e. the output of Serialize
+    Serialize,
+    // Types sent to Rust, i.e. the input of Deserialize
+    Deserialize,
+    // Types that work both ways
+    Both,
+}
+
+impl Direction {
+    fn parse(s: &str) -> Option<Direction> {
+        match s {
+            "serialize" => Some(Direction::Serialize),
+            "deserialize" => Some(Direction::Deserialize),
+            "both" => Some(Direction::Both),
+            _ => None,
+        }
+    }
+
+    /// Check if something with the given skip attributes is left out
+    /// of the JSON in this direction
+    fn skips(self, skip_serializing: bool, skip_deserializing: bool) -> bool {
+        match self {
+            Direction::Serialize => skip_serializing,
+            Direction::Deserialize => skip_deserializing,
+            Direction::Both => skip_serializing && skip_deserializing,
+        }
     }
 }
 
 #[derive(Debug)]
+struct Options {
+    direction: Direction,
+    // Emit `field?: T` rather than `field?: T | null` for Option
+    // fields with skip_serializing_if
+    collapse_option: bool,
+    // Names of all structs emitted as interfaces, filled in once
+    // all the input is loaded. Used to resolve flattened fields.
+    interfaces: HashSet<String>,
+    // Names of all types defined in the input, filled in once all
+    // the input is loaded. They take precedence over well-known
+    // types with the same name.
+    defined_types: HashSet<String>,
+    // Type of the unknown variant names accepted by enums with a
+    // #[serde(other)] variant
+    other_fallback: String,
+    // TypeScript types for fields with #[serde(with = "...")], keyed
+    // by module path
+    with_types: HashMap<String, String>,
+    // Extra derive names that mean a struct is serializable, for
+    // crates that re-export serde's derives under another name
+    derive_aliases: Vec<String>,
+    int64: BigIntType,
+    int128: BigIntType,
+    // Type of Decimal and BigDecimal
+    decimal: String,
+    // Fixed-size arrays up to this length are emitted as tuples
+    max_tuple_len: usize,
+    // Type of serde_json::Value
+    json_value: String,
+    // Map Uuid to a branded alias rather than plain string
+    uuid_alias: bool,
+    // Map Url to an alias rather than plain string
+    url_alias: bool,
+    // Map chrono's naive date and time types to aliases like IsoDate
+    // rather than plain string
+    date_aliases: bool,
+    // Types of DateTime by offset, e.g. FixedOffset. Any offset not
+    // listed uses the alias for DateTime<Utc>.
+    datetime_types: HashMap<String, String>,
+    // Name and definition of the alias for DateTime<Utc>, which is
+    // only emitted if it's used
+    datetime_alias: (String, String),
+    // Types of time crate types by name, e.g. OffsetDateTime, for
+    // when they're serialized as timestamps
+    time_types: HashMap<String, String>,
+    // Type of chrono::Duration: ISO 8601 strings or milliseconds
+    chrono_duration: String,
+    // Type of byte buffers, which JSON serializers write as arrays
+    // of numbers unless configured to use base64
+    bytes: String,
+    // Names of bitflags types, which serialize as numbers or as
+    // strings of flag names depending on the bitflags version
+    bitflags: Vec<String>,
+    bitflags_type: String,
+    // Emit a const object with the values of each bitflags! type
+    bitflags_consts: bool,
+    // Emit bson's ObjectId and DateTime as strings rather than
+    // extended JSON objects
+    bson_plain: bool,
+    // Extra types that serialize as strings
+    string_types: Vec<String>,
+    // Extra generic types that serialize the same as what they wrap
+    wrapper_types: Vec<String>,
+    // Types of trait objects by trait name, e.g. Error for
+    // `Box<dyn Error>`. Unlisted traits are unknown.
+    trait_types: HashMap<String, String>,
+    // Type of `()` and unit structs, which serialize as null
+    unit_type: String,
+    // Emit a concrete type for each instantiation of a generic type
+    // instead of TypeScript generics
+    monomorphize: bool,
+    // Names of type aliases that refer to themselves, which are
+    // emitted as unknown. Filled in once all the input is loaded.
+    alias_cycles: HashSet<String>,
+    // Emit consts with literal values
+    include_consts: bool,
+    // Emit immutable statics with literal values
+    include_statics: bool,
+    // Wrap the types in inline modules in namespaces
+    module_namespaces: bool,
+    // Names of all inline modules, filled in once all the input is
+    // loaded
+    modules: HashSet<String>,
+    // Emit a const object with the discriminants of C-like enums
+    enum_consts: bool,
+    // Extra union member for #[non_exhaustive] enums, for variants
+    // added later
+    non_exhaustive_fallback: Option<String>,
+    // Emit items marked #[doc(hidden)]
+    include_hidden: bool,
+    // Attributes that mark an item to be skipped, like `ts_skip`
+    exclude_attrs: Vec<String>,
+    // Names of items to skip
+    exclude: HashSet<String>,
+    // Only emit `pub` items
+    only_pub: bool,
+    // With `only_pub`, also emit `pub(crate)` items
+    pub_crate: bool,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            direction: Direction::Both,
+            collapse_option: false,
+            interfaces: HashSet::new(),
+            defined_types: HashSet::new(),
+            // The `& {}` keeps editors suggesting the known variants
+            other_fallback: "(string & {})".to_string(),
+            with_types: HashMap::new(),
+            derive_aliases: Vec::new(),
+            int64: BigIntType::Number,
+            int128: BigIntType::Number,
+            decimal: "string".to_string(),
+            max_tuple_len: 4,
+            json_value: "unknown".to_string(),
+            uuid_alias: false,
+            url_alias: false,
+            date_aliases: false,
+            datetime_types: HashMap::new(),
+            datetime_alias: ("DateTimeUtc".to_string(), "string".to_string()),
+            time_types: HashMap::new(),
+            chrono_duration: "string".to_string(),
+            bytes: "number[]".to_string(),
+            bitflags: Vec::new(),
+            bitflags_type: "number".to_string(),
+            bitflags_consts: false,
+            bson_plain: false,
+            string_types: Vec::new(),
+            wrapper_types: Vec::new(),
+            trait_types: HashMap::new(),
+            unit_type: "null".to_string(),
+            monomorphize: false,
+            alias_cycles: HashSet::new(),
+            include_consts: false,
+            include_statics: false,
+            module_namespaces: false,
+            modules: HashSet::new(),
+            enum_consts: false,
+            non_exhaustive_fallback: None,
+            include_hidden: false,
+            exclude_attrs: Vec::new(),
+            exclude: HashSet::new(),
+            only_pub: false,
+            pub_crate: false,
+        }
+    }
+}
+
+/// TypeScript type for integers that may not fit in a number
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum BigIntType {
+    // Loses precision for large values
+    Number,
+    // Needs serde_with's DisplayFromStr or similar on the Rust side
+    String,
+    // Needs a custom JSON parser on the TypeScript side
+    BigInt,
+}
+
+impl BigIntType {
+    fn parse(s: &str) -> Option<BigIntType> {
+        match s {
+            "number" => Some(BigIntType::Number),
+            "string" => Some(BigIntType::String),
+            "bigint" => Some(BigIntType::BigInt),
+            _ => None,
+        }
+    }
+
+    fn to_ts(self) -> &'static str {
+        match self {
+            BigIntType::Number => "number",
+            BigIntType::String => "string",
+            BigIntType::BigInt => "bigint",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
 struct SimpleStruct {
     name: String,
     fields: Vec<SimpleField>,
+    rename_all: Option<RenameRule>,
+    // From #[serde(transparent)]
+    transparent: bool,
+    // From #[serde(deny_unknown_fields)]
+    deny_unknown_fields: bool,
+    // Type parameters. Bounds (including #[serde(bound)]) don't
+    // matter for the JSON, so they're dropped.
+    generics: Vec<TypeParam>,
+    // Set for unit structs like `struct Heartbeat;`
+    unit: bool,
+    // From #[non_exhaustive], fields may be added
+    non_exhaustive: bool,
+    // Lines of the doc comment
+    doc: Vec<String>,
+}
+
+/// A type alias, e.g. `type Ids = Vec<u64>;`
+#[derive(Clone, Debug)]
+struct SimpleAlias {
+    name: String,
+    generics: Vec<TypeParam>,
+    ty: SimpleType,
+}
+
+impl SimpleAlias {
+    fn new(t: &syn::ItemType) -> Option<SimpleAlias> {
+        let name = ident_name(&t.ident);
+        match SimpleType::from_syn_type(&t.ty) {
+            Ok(ty) => Some(SimpleAlias {
+                name,
+                generics: type_params(&t.generics),
+                ty,
+            }),
+            Err(err) => {
+                eprintln!("{}: {}", name, err);
+                None
+            }
+        }
+    }
+
+    fn to_ts(&self, opts: &Options) -> String {
+        let ty = if opts.alias_cycles.contains(&self.name) {
+            "unknown".to_string()
+        } else {
+            self.ty.to_ts(opts)
+        };
+        format!(
+            "export type {} = {};\n",
+            ts_name(&self.name, &self.generics, opts),
+            ty
+        )
+    }
 }
 
+/// A const or static with a literal value
 #[derive(Debug)]
+struct SimpleConst {
+    name: String,
+    value: String,
+}
+
+impl SimpleConst {
+    fn new(ident: &syn::Ident, expr: &syn::Expr) -> Option<SimpleConst> {
+        let value = literal_to_ts(expr);
+        if value.is_none() {
+            eprintln!("{}: only literal values can be exported", ident);
+        }
+        Some(SimpleConst {
+            name: ident_name(ident),
+            value: value?,
+        })
+    }
+
+    fn to_ts(&self) -> String {
+        format!("export const {} = {};\n", self.name, self.value)
+    }
+}
+
+/// Get the TypeScript form of an integer, float, string, or bool
+/// literal
+fn literal_to_ts(expr: &syn::Expr) -> Option<String> {
+    match expr {
+        syn::Expr::Lit(syn::ExprLit { lit, .. }) => match lit {
+            syn::Lit::Int(i) => Some(i.value().to_string()),
+            syn::Lit::Float(f) => Some(f.value().to_string()),
+            // Rust's escapes are all valid in JavaScript strings
+            syn::Lit::Str(s) => Some(format!("{:?}", s.value())),
+            syn::Lit::Char(c) => Some(format!("{:?}", c.value().to_string())),
+            syn::Lit::Bool(b) => Some(b.value.to_string()),
+            _ => None,
+        },
+        syn::Expr::Unary(syn::ExprUnary {
+            op: syn::UnOp::Neg(_),
+            expr,
+            ..
+        }) => literal_to_ts(expr).map(|v| format!("-{}", v)),
+        _ => None,
+    }
+}
+
+/// A type parameter of a struct or enum
+#[derive(Clone, Debug)]
+struct TypeParam {
+    name: String,
+    // From `T = ...`
+    default: Option<SimpleType>,
+}
+
+/// Get the type parameters of a struct or enum
+fn type_params(generics: &syn::Generics) -> Vec<TypeParam> {
+    generics
+        .type_params()
+        .map(|p| TypeParam {
+            name: ident_name(&p.ident),
+            default: p
+                .default
+                .as_ref()
+                .and_then(|ty| SimpleType::from_syn_type(ty).ok()),
+        })
+        .collect()
+}
+
+/// Get the name of a type with its type parameters, e.g.
+/// `Response<T = null>`
+fn ts_name(name: &str, params: &[TypeParam], opts: &Options) -> String {
+    if params.is_empty() {
+        return name.to_string();
+    }
+    let params = params
+        .iter()
+        .map(|p| match &p.default {
+            Some(ty) => format!("{} = {}", p.name, ty.to_ts(opts)),
+            None => p.name.to_string(),
+        })
+        .collect::<Vec<_>>();
+    format!("{}<{}>", name, params.join(", "))
+}
+
+#[derive(Clone, Debug)]
 struct SimpleVariant {
     name: String,
-    fields: Vec<SimpleType>,
-    // TODO: literal values
+    fields: Vec<SimpleField>,
+    // Set for struct variants, whose fields have names
+    named: bool,
+    // From #[serde(rename_all = "...")] on the variant, renames the
+    // fields of a struct variant
+    rename_all: Option<RenameRule>,
+    // Explicit discriminant, e.g. the 1 in `Red = 1`
+    discriminant: Option<i64>,
+    // From #[serde(rename = "...")], takes precedence over the
+    // enum's rename_all
+    rename: Option<String>,
+    // From #[serde(alias = "...")]
+    aliases: Vec<String>,
+    // From #[serde(other)], deserialized from any unknown variant
+    other: bool,
+    // From #[serde(untagged)] on the variant
+    untagged: bool,
+    // From #[serde(skip_serializing)], #[serde(skip_deserializing)],
+    // or #[serde(skip)] for both
+    skip_serializing: bool,
+    skip_deserializing: bool,
+    // Lines of the doc comment
+    doc: Vec<String>,
 }
 
 impl SimpleVariant {
-    fn new(name: String, fields: Vec<SimpleType>) -> SimpleVariant {
-        SimpleVariant { name, fields }
+    fn new(name: String, fields: Vec<SimpleField>) -> SimpleVariant {
+        SimpleVariant {
+            name,
+            fields,
+            named: false,
+            rename_all: None,
+            discriminant: None,
+            rename: None,
+            aliases: Vec::new(),
+            other: false,
+            untagged: false,
+            skip_serializing: false,
+            skip_deserializing: false,
+            doc: Vec::new(),
+        }
     }
 }
 
-#[derive(Debug)]
+/// How an enum is represented in JSON, see
+/// https://serde.rs/enum-representations.html
+#[derive(Clone, Debug, PartialEq)]
+enum EnumTagging {
+    // { "Variant": ... }, the default
+    External,
+    // { "tag": "Variant", ...fields }, from #[serde(tag = "...")]
+    Internal { tag: String },
+    // { "tag": "Variant", "content": ... }, from
+    // #[serde(tag = "...", content = "...")]
+    Adjacent { tag: String, content: String },
+    // Just the variant's contents, from #[serde(untagged)]
+    Untagged,
+}
+
+#[derive(Clone, Debug)]
 struct SimpleEnum {
     name: String,
     variants: Vec<SimpleVariant>,
+    rename_all: Option<RenameRule>,
+    tagging: EnumTagging,
+    // Derives Serialize_repr or Deserialize_repr, so the variants
+    // are serialized as their discriminants
+    repr: bool,
+    // From #[serde(rename_all_fields = "...")], renames the fields of
+    // all struct variants
+    rename_all_fields: Option<RenameRule>,
+    generics: Vec<TypeParam>,
+    // From #[non_exhaustive], variants may be added
+    non_exhaustive: bool,
+    // Lines of the doc comment
+    doc: Vec<String>,
+}
+
+/// Case conversion from `#[serde(rename_all = "...")]`
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum RenameRule {
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
+}
+
+impl RenameRule {
+    fn parse(s: &str) -> Option<RenameRule> {
+        match s {
+            "lowercase" => Some(RenameRule::Lower),
+            "UPPERCASE" => Some(RenameRule::Upper),
+            "PascalCase" => Some(RenameRule::Pascal),
+            "camelCase" => Some(RenameRule::Camel),
+            "snake_case" => Some(RenameRule::Snake),
+            "SCREAMING_SNAKE_CASE" => Some(RenameRule::ScreamingSnake),
+            "kebab-case" => Some(RenameRule::Kebab),
+            "SCREAMING-KEBAB-CASE" => Some(RenameRule::ScreamingKebab),
+            _ => None,
+        }
+    }
+
+    /// Convert a snake_case field name, same as serde does
+    fn apply_to_field(self, field: &str) -> String {
+        match self {
+            RenameRule::Lower | RenameRule::Snake => field.to_string(),
+            RenameRule::Upper | RenameRule::ScreamingSnake => field.to_ascii_uppercase(),
+            RenameRule::Pascal => {
+                let mut out = String::new();
+                let mut capitalize = true;
+                for c in field.chars() {
+                    if c == '_' {
+                        capitalize = true;
+                    } else if capitalize {
+                        out.push(c.to_ascii_uppercase());
+                        capitalize = false;
+                    } else {
+                        out.push(c);
+                    }
+                }
+                out
+            }
+            RenameRule::Camel => {
+                let pascal = RenameRule::Pascal.apply_to_field(field);
+                lowercase_first(&pascal)
+            }
+            RenameRule::Kebab => field.replace('_', "-"),
+            RenameRule::ScreamingKebab => field.to_ascii_uppercase().replace('_', "-"),
+        }
+    }
+
+    /// Convert a PascalCase variant name, same as serde does
+    fn apply_to_variant(self, variant: &str) -> String {
+        match self {
+            RenameRule::Pascal => variant.to_string(),
+            RenameRule::Lower => variant.to_ascii_lowercase(),
+            RenameRule::Upper => variant.to_ascii_uppercase(),
+            RenameRule::Camel => lowercase_first(variant),
+            RenameRule::Snake => {
+                let mut out = String::new();
+                for (i, c) in variant.chars().enumerate() {
+                    if i > 0 && c.is_uppercase() {
+                        out.push('_');
+                    }
+                    out.push(c.to_ascii_lowercase());
+                }
+                out
+            }
+            RenameRule::ScreamingSnake => RenameRule::Snake
+                .apply_to_variant(variant)
+                .to_ascii_uppercase(),
+            RenameRule::Kebab => RenameRule::Snake
+                .apply_to_variant(variant)
+                .replace('_', "-"),
+            RenameRule::ScreamingKebab => RenameRule::ScreamingSnake
+                .apply_to_variant(variant)
+                .replace('_', "-"),
+        }
+    }
+}
+
+fn lowercase_first(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) => c.to_lowercase().chain(chars).collect(),
+        None => String::new(),
+    }
 }
 
-const NUMERIC_TYPES: [&'static str; 10] = [
-    "i8", "i16", "i32", "i64", "u8", "u16", "u32", "u64", "f32", "f64",
+const NUMERIC_TYPES: [&str; 10] = [
+    "i8", "i16", "i32", "isize", "u8", "u16", "u32", "usize", "f32", "f64",
+];
+
+// Types that serialize as strings
+const STRING_TYPES: [&str; 17] = [
+    "String",
+    "str",
+    "char",
+    "Uuid",
+    "Url",
+    // Paths fail to serialize if they aren't valid UTF-8
+    "PathBuf",
+    "Path",
+    "Utf8PathBuf",
+    "Utf8Path",
+    "IpAddr",
+    "Ipv4Addr",
+    "Ipv6Addr",
+    "SocketAddr",
+    "SocketAddrV4",
+    "SocketAddrV6",
+    // semver types, e.g. "1.2.3" and ">=1.2, <2"
+    "Version",
+    "VersionReq",
+];
+
+// Inline and shared string types. Some take a capacity or mode
+// argument, e.g. ArrayString<16> or SmartString<LazyCompact>. Names
+// too generic to assume, like hipstr's Str, can be added with
+// --string-type.
+const SMALL_STRING_TYPES: [&str; 6] = [
+    "SmartString",
+    "CompactString",
+    "ArrayString",
+    "SmolStr",
+    "EcoString",
+    "ArcStr",
+];
+
+// Collections that serialize as JSON arrays
+const SEQUENCE_TYPES: [&str; 6] = [
+    "Vec",
+    "VecDeque",
+    "LinkedList",
+    "HashSet",
+    "BTreeSet",
+    "IndexSet",
+];
+
+// Collections that serialize as JSON objects
+const MAP_TYPES: [&str; 3] = ["HashMap", "BTreeMap", "IndexMap"];
+
+// Fixed-capacity vectors, which serialize as JSON arrays. SmallVec
+// and TinyVec take an array type like `[T; 4]` rather than the
+// element type.
+const FIXED_CAPACITY_TYPES: [&str; 3] = ["SmallVec", "ArrayVec", "TinyVec"];
+
+// Marker traits that are ignored in trait objects
+const AUTO_TRAITS: [&str; 4] = ["Send", "Sync", "Unpin", "UnwindSafe"];
+
+// Types that serialize the same as what they wrap
+const WRAPPER_TYPES: [&str; 13] = [
+    "Box",
+    "Rc",
+    "Arc",
+    "Cow",
+    "Cell",
+    "RefCell",
+    "Mutex",
+    "RwLock",
+    "NonZero",
+    "Wrapping",
+    "Saturating",
+    "OrderedFloat",
+    "NotNan",
+];
+
+// These can't be represented exactly by a JavaScript number
+const INT64_TYPES: [&str; 2] = ["i64", "u64"];
+const INT128_TYPES: [&str; 2] = ["i128", "u128"];
+
+// chrono types that serialize as ISO 8601 strings, with the
+// optional alias for each
+const NAIVE_DATE_TYPES: [(&str, &str); 3] = [
+    ("NaiveDate", "IsoDate"),
+    ("NaiveDateTime", "IsoDateTime"),
+    ("NaiveTime", "IsoTime"),
+];
+
+// Types from the time crate, which serialize as strings by default.
+// The generic names in QUALIFIED_TIME_TYPES are only matched with a
+// `time::` prefix.
+const TIME_TYPES: [&str; 2] = ["OffsetDateTime", "PrimitiveDateTime"];
+const QUALIFIED_TIME_TYPES: [&str; 3] = ["Date", "Time", "Duration"];
+
+// Types from std::time and how serde serializes them
+const STD_TIME_TYPES: [(&str, &str); 2] = [
+    ("Duration", "{ secs: number; nanos: number }"),
+    (
+        "SystemTime",
+        "{ secs_since_epoch: number; nanos_since_epoch: number }",
+    ),
+];
+
+// rust_decimal and bigdecimal types, which serialize as strings by
+// default and as numbers with some crate features
+const DECIMAL_TYPES: [&str; 2] = ["Decimal", "BigDecimal"];
+
+// bson types as written by serde_json, which uses MongoDB's extended
+// JSON, and as plain strings when serialized with bson's serde
+// helpers
+const BSON_TYPES: [(&str, &str, &str); 2] = [
+    ("ObjectId", "{ $oid: string }", "string"),
+    ("DateTime", "{ $date: { $numberLong: string } }", "string"),
 ];
 
+// Byte buffers from the bytes and serde_bytes crates
+const BYTES_TYPES: [&str; 3] = ["Bytes", "BytesMut", "ByteBuf"];
+
+/// Get the integer type of a NonZero type, e.g. `u32` for
+/// `NonZeroU32`
+fn nonzero_int(name: &str) -> Option<String> {
+    if !name.starts_with("NonZero") {
+        return None;
+    }
+    let int = name["NonZero".len()..].to_lowercase();
+    if NUMERIC_TYPES.contains(&int.as_str())
+        || INT64_TYPES.contains(&int.as_str())
+        || INT128_TYPES.contains(&int.as_str())
+    {
+        Some(int)
+    } else {
+        None
+    }
+}
+
+/// Get the array type for an element type, e.g. `number[]`
+fn array_of(mut elem: String) -> String {
+    if elem.contains(' ') {
+        elem = format!("({})", elem);
+    }
+    format!("{}[]", elem)
+}
+
+/// Get a property name as it can appear in an object type, quoted
+/// unless it's a valid identifier
+fn property_name(name: &str) -> String {
+    let is_ident_char = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '$';
+    let valid = match name.chars().next() {
+        Some(first) => !first.is_ascii_digit() && name.chars().all(is_ident_char),
+        None => false,
+    };
+    if valid {
+        name.to_string()
+    } else {
+        format!("{:?}", name)
+    }
+}
+
+/// Get the name of an identifier without the `r#` of raw
+/// identifiers like `r#type`
+fn ident_name(ident: &syn::Ident) -> String {
+    let name = ident.to_string();
+    match name.strip_prefix("r#") {
+        Some(name) => name.to_string(),
+        None => name,
+    }
+}
+
+/// Get the value of an array length or const generic argument,
+/// either a number or the name of a constant
+fn const_value(expr: &syn::Expr) -> Option<String> {
+    match expr {
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Int(i),
+            ..
+        }) => Some(i.value().to_string()),
+        syn::Expr::Path(p) if p.path.segments.len() == 1 => {
+            Some(ident_name(&p.path.segments[0].ident))
+        }
+        // `{ N }`, which const generic arguments need when they
+        // aren't literals
+        syn::Expr::Block(b) if b.block.stmts.len() == 1 => match &b.block.stmts[0] {
+            syn::Stmt::Expr(expr) => const_value(expr),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Get the text of a const generic argument, which can be any
+/// expression
+fn const_arg(expr: &syn::Expr) -> String {
+    if let syn::Expr::Block(b) = expr {
+        if let [syn::Stmt::Expr(expr)] = b.block.stmts.as_slice() {
+            return const_arg(expr);
+        }
+    }
+    const_value(expr).unwrap_or_else(|| expr.into_token_stream().to_string())
+}
+
+/// Get the name of a constant passed as a generic argument without
+/// braces, like the `ROWS` in `Matrix<f64, ROWS>`. That parses the
+/// same as a type argument, so SCREAMING_CASE names are taken to be
+/// constants. Single letters like `T` are still type parameters.
+fn const_name(arg: &syn::GenericArgument) -> Option<String> {
+    let path = match arg {
+        syn::GenericArgument::Type(syn::Type::Path(p)) if p.qself.is_none() => &p.path,
+        _ => return None,
+    };
+    if path.leading_colon.is_some() || path.segments.len() != 1 {
+        return None;
+    }
+    let seg = &path.segments[0];
+    let name = ident_name(&seg.ident);
+    let screaming = name.len() > 1
+        && name.starts_with(|c: char| c.is_ascii_uppercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_');
+    if seg.arguments.is_empty() && screaming {
+        Some(name)
+    } else {
+        None
+    }
+}
+
 impl SimpleType {
     fn new(path: Vec<String>, generic_args: Vec<SimpleType>) -> SimpleType {
-        SimpleType { path, generic_args }
+        SimpleType {
+            path,
+            generic_args,
+            array_len: None,
+            const_args: Vec::new(),
+            trait_object: false,
+        }
+    }
+
+    fn array(elem: SimpleType, len: String) -> SimpleType {
+        SimpleType {
+            path: Vec::new(),
+            generic_args: vec![elem],
+            array_len: Some(len),
+            const_args: Vec::new(),
+            trait_object: false,
+        }
     }
 
     fn from_syn_type(ty: &syn::Type) -> Result<SimpleType, SimpleTypeError> {
+        if let syn::Type::Reference(r) = ty {
+            // References serialize the same as what they point to
+            return SimpleType::from_syn_type(&r.elem);
+        }
+        if let syn::Type::Tuple(t) = ty {
+            if t.elems.is_empty() {
+                return Ok(SimpleType::new(vec!["()".to_string()], Vec::new()));
+            }
+        }
+        if let syn::Type::Slice(slice) = ty {
+            let elem = SimpleType::from_syn_type(&slice.elem)?;
+            return Ok(SimpleType::new(vec!["Vec".to_string()], vec![elem]));
+        }
+        match ty {
+            syn::Type::ImplTrait(_) => return Err(SimpleTypeError::ImplTrait),
+            syn::Type::BareFn(_) => return Err(SimpleTypeError::FnPointer),
+            syn::Type::Ptr(_) => return Err(SimpleTypeError::RawPointer),
+            _ => {}
+        }
+        if let syn::Type::TraitObject(obj) = ty {
+            // Auto traits like Send don't say anything about the data
+            let main = obj.bounds.iter().find_map(|b| match b {
+                syn::TypeParamBound::Trait(t) => {
+                    let last = t.path.segments.iter().last()?;
+                    if AUTO_TRAITS.contains(&ident_name(&last.ident).as_str()) {
+                        None
+                    } else {
+                        Some(&t.path)
+                    }
+                }
+                _ => None,
+            });
+            let path = match main {
+                Some(path) => path.segments.iter().map(|s| ident_name(&s.ident)).collect(),
+                None => return Err(SimpleTypeError::TypeIsNotPath),
+            };
+            let mut st = SimpleType::new(path, Vec::new());
+            st.trait_object = true;
+            return Ok(st);
+        }
+        if let syn::Type::Array(arr) = ty {
+            let elem = SimpleType::from_syn_type(&arr.elem)?;
+            let len = const_value(&arr.len).ok_or(SimpleTypeError::InvalidArrayLen)?;
+            return Ok(SimpleType::array(elem, len));
+        }
         if let syn::Type::Path(path) = ty {
             if path.qself.is_some() {
                 return Err(SimpleTypeError::QSelf);
@@ -83,12 +1044,19 @@ impl SimpleType {
                     // segment
                     return Err(SimpleTypeError::EarlyGenericArgs);
                 }
-                st.path.push(seg.ident.to_string());
+                st.path.push(ident_name(&seg.ident));
 
                 if let syn::PathArguments::AngleBracketed(args) = &seg.arguments {
                     for arg in args.args.iter() {
-                        if let syn::GenericArgument::Type(ty) = arg {
-                            match SimpleType::from_syn_type(&ty) {
+                        if let syn::GenericArgument::Lifetime(_) = arg {
+                            // Lifetimes don't affect serialization
+                            continue;
+                        } else if let syn::GenericArgument::Const(expr) = arg {
+                            st.const_args.push(const_arg(expr));
+                        } else if let Some(name) = const_name(arg) {
+                            st.const_args.push(name);
+                        } else if let syn::GenericArgument::Type(ty) = arg {
+                            match SimpleType::from_syn_type(ty) {
                                 Ok(arg) => {
                                     st.generic_args.push(arg);
                                 }
@@ -111,326 +1079,4985 @@ impl SimpleType {
         }
     }
 
-    fn is_datetime_utc(&self) -> bool {
-        self.path == ["DateTime"]
-            && self.generic_args.len() == 1
-            && self.generic_args[0].path == ["Utc"]
-            && self.generic_args[0].generic_args.is_empty()
-    }
-
-    fn to_ts(&self) -> String {
-        if self.path == ["Option"] && self.generic_args.len() == 1 {
-            format!("{} | null", self.generic_args[0].to_ts())
-        } else if self.path == ["Vec"] && self.generic_args.len() == 1 {
-            let mut inner = self.generic_args[0].to_ts();
-            if inner.contains(' ') {
-                inner = format!("({})", inner);
-            }
-            format!("{}[]", inner)
-        } else if self.is_datetime_utc() {
-            "DateTimeUtc".to_string()
-        } else if self.path == ["HashMap"] && self.generic_args.len() == 2 {
-            format!(
-                "Record<{}, {}>",
-                self.generic_args[0].to_ts(),
-                self.generic_args[1].to_ts()
-            )
-        } else if self.generic_args.len() == 0 {
-            if self.path.len() == 1 {
-                if NUMERIC_TYPES.contains(&self.path[0].as_str()) {
-                    "number".to_string()
-                } else if self.path[0] == "String" {
-                    "string".to_string()
-                } else {
-                    self.path[0].to_string()
-                }
-            } else {
-                "TODO1".to_string()
+    /// Get the element type of a fixed-capacity vector
+    fn fixed_capacity_elem(&self) -> Option<&SimpleType> {
+        let is_fixed = match self.path.as_slice() {
+            [name] => {
+                FIXED_CAPACITY_TYPES.contains(&name.as_str())
+                    // heapless::Vec<T, N> imported as Vec
+                    || (name == "Vec" && self.generic_args.len() == 2)
             }
+            [krate, name] => {
+                FIXED_CAPACITY_TYPES.contains(&name.as_str())
+                    || (krate == "heapless" && name == "Vec")
+            }
+            _ => false,
+        };
+        if !is_fixed {
+            return None;
+        }
+        let first = self.generic_args.first()?;
+        if first.array_len.is_some() {
+            Some(&first.generic_args[0])
         } else {
-            "TODO2".to_string()
+            Some(first)
         }
     }
-}
 
-impl SimpleEnum {
-    fn from_syn_type(e: &syn::ItemEnum) -> Option<SimpleEnum> {
-        let name = e.ident.to_string();
-        let mut se = SimpleEnum {
-            name,
-            variants: Vec::new(),
+    fn is_small_string(&self, opts: &Options) -> bool {
+        self.path.len() == 1
+            && (SMALL_STRING_TYPES.contains(&self.path[0].as_str())
+                || opts.string_types.contains(&self.path[0]))
+    }
+
+    fn is_wrapper(&self, opts: &Options) -> bool {
+        self.path.len() == 1
+            && (WRAPPER_TYPES.contains(&self.path[0].as_str())
+                || opts.wrapper_types.contains(&self.path[0]))
+            && self.generic_args.len() == 1
+    }
+
+    fn is_sequence(&self) -> bool {
+        self.path.len() == 1
+            && SEQUENCE_TYPES.contains(&self.path[0].as_str())
+            && self.generic_args.len() == 1
+    }
+
+    /// Check for a serde_json type, e.g. `Value` or `serde_json::Value`
+    fn is_json_type(&self, name: &str) -> bool {
+        self.path == [name] || self.path == ["serde_json", name]
+    }
+
+    fn is_either(&self) -> bool {
+        (self.path == ["Either"] || self.path == ["either", "Either"])
+            && self.generic_args.len() == 2
+    }
+
+    fn is_range(&self) -> bool {
+        let name = match self.path.as_slice() {
+            [name] => name,
+            [krate, module, name] if (krate == "std" || krate == "core") && module == "ops" => name,
+            _ => return false,
         };
-        for v in e.variants.iter() {
-            let mut fields = Vec::new();
-            for f in v.fields.iter() {
-                if let Ok(ty) = SimpleType::from_syn_type(&f.ty) {
-                    fields.push(ty);
-                } else {
-                    return None;
-                }
-            }
-            se.variants
-                .push(SimpleVariant::new(v.ident.to_string(), fields));
-        }
-        Some(se)
+        (name == "Range" || name == "RangeInclusive") && self.generic_args.len() == 1
     }
 
-    fn to_ts(&self) -> String {
-        let mut out = format!("export type {} =\n", self.name);
-        let mut variants = Vec::new();
-        for v in self.variants.iter() {
-            if v.fields.len() == 0 {
-                variants.push(format!("  \"{}\"", v.name));
-            } else if v.fields.len() == 1 {
-                variants.push(format!("  {{ {}: {} }}", v.name, v.fields[0].to_ts()));
-            } else {
-                let fields = v.fields.iter().map(|f| f.to_ts()).collect::<Vec<String>>();
-                variants.push(format!("  {{ {}: [{}] }}", v.name, fields.join(", ")));
-            }
-        }
-        out += &variants.join(" |\n");
-        out += ";\n";
-        out
+    fn is_phantom_data(&self) -> bool {
+        self.path.last().map(String::as_str) == Some("PhantomData")
     }
-}
 
-fn attr_to_derives(attr: &syn::Attribute) -> Vec<String> {
-    let mut derives = Vec::new();
-    if let Ok(syn::Meta::List(lst)) = attr.parse_meta() {
-        if lst.ident.to_string() != "derive" {
-            return derives;
+    fn is_option(&self) -> bool {
+        self.path == ["Option"] && self.generic_args.len() == 1
+    }
+
+    fn is_map(&self) -> bool {
+        self.path.len() == 1
+            && MAP_TYPES.contains(&self.path[0].as_str())
+            && self.generic_args.len() == 2
+    }
+
+    /// Get the name of a type from the time crate
+    fn time_type(&self) -> Option<&str> {
+        if !self.generic_args.is_empty() {
+            return None;
         }
-        for child in lst.nested.iter() {
-            if let syn::NestedMeta::Meta(syn::Meta::Word(ident)) = child {
-                derives.push(ident.to_string());
+        match self.path.as_slice() {
+            [name] if TIME_TYPES.contains(&name.as_str()) => Some(name),
+            [krate, name]
+                if krate == "time"
+                    && (TIME_TYPES.contains(&name.as_str())
+                        || QUALIFIED_TIME_TYPES.contains(&name.as_str())) =>
+            {
+                Some(name)
             }
+            _ => None,
         }
     }
-    derives
-}
 
-impl SimpleStruct {
-    fn new(s: &syn::ItemStruct) -> Option<SimpleStruct> {
-        let name = s.ident.to_string();
-        let mut ss = SimpleStruct {
-            name,
-            fields: Vec::new(),
-        };
-        let mut derives = Vec::new();
-        for attr in s.attrs.iter() {
-            derives.append(&mut attr_to_derives(&attr));
-        }
-        // Skip structs that don't derive Deserialize or
-        // Serialize. These traits might be manually implemented, but
-        // then it's not clear if we can meaningfully autogenerate a
-        // TypeScript type.
-        if !derives.contains(&"Deserialize".to_string())
-            && !derives.contains(&"Serialize".to_string())
-        {
+    /// Get the serialized shape of a std::time type
+    fn std_time_type(&self) -> Option<&'static str> {
+        if !self.generic_args.is_empty() {
             return None;
         }
-        for field in s.fields.iter() {
-            let name = field.ident.as_ref().map(|i| i.to_string());
-            match SimpleType::from_syn_type(&field.ty) {
-                Ok(st) => {
-                    ss.fields.push(SimpleField::new(name, st));
-                }
-                Err(err) => {
-                    println!("{:?}: {:?}", name, err);
-                }
+        let name = match self.path.as_slice() {
+            [name] => name,
+            [krate, module, name] if (krate == "std" || krate == "core") && module == "time" => {
+                name
             }
-        }
-        Some(ss)
+            _ => return None,
+        };
+        STD_TIME_TYPES
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, ty)| *ty)
     }
 
-    fn to_ts(&self) -> String {
-        if self.fields.len() == 0 {
-            panic!("empty structs not supported");
-        } else if self.fields.len() == 1 && self.fields[0].name.is_none() {
-            format!(
-                "export type {} = {};\n",
-                self.name,
-                self.fields[0].ty.to_ts()
-            )
-        } else {
-            let mut out = format!("export interface {} {{\n", self.name);
-            for f in self.fields.iter() {
-                out += &format!("  {}: {};\n", f.name.as_ref().unwrap(), f.ty.to_ts());
-            }
-            out += "}\n";
-            out
+    /// Check if the type is chrono's Duration, also called TimeDelta
+    fn is_chrono_duration(&self) -> bool {
+        if !self.generic_args.is_empty() {
+            return false;
+        }
+        match self.path.as_slice() {
+            [name] => name == "TimeDelta",
+            [krate, name] => krate == "chrono" && (name == "Duration" || name == "TimeDelta"),
+            _ => false,
         }
     }
-}
 
-struct SimpleFile {
+    /// Get the type of a bson ObjectId or DateTime. chrono's
+    /// DateTime always has an offset argument, so a bare DateTime is
+    /// bson's unless the input defines one, e.g. as an alias.
+    fn bson_type(&self, opts: &Options) -> Option<&'static str> {
+        if !self.generic_args.is_empty() {
+            return None;
+        }
+        let name = match self.path.as_slice() {
+            [name] => name,
+            [krate, name] if krate == "bson" => name,
+            [krate, module, name] if krate == "bson" && module == "oid" => name,
+            _ => return None,
+        };
+        BSON_TYPES
+            .iter()
+            .find(|(n, _, _)| n == name)
+            .map(|(_, extended, plain)| if opts.bson_plain { *plain } else { *extended })
+    }
+
+    /// Get the offset type of a chrono DateTime, e.g. `Utc`
+    fn datetime_offset(&self) -> Option<&str> {
+        if self.path == ["DateTime"]
+            && self.generic_args.len() == 1
+            && self.generic_args[0].generic_args.is_empty()
+        {
+            // The offset may be qualified, e.g. chrono::Utc
+            self.generic_args[0].path.last().map(String::as_str)
+        } else {
+            None
+        }
+    }
+
+    /// Get the names of trait objects in the type that have no
+    /// configured type
+    fn unmapped_traits(&self, opts: &Options) -> Vec<String> {
+        let mut traits = Vec::new();
+        if self.trait_object {
+            let name = self.path.last().unwrap();
+            if !opts.trait_types.contains_key(name) {
+                traits.push(name.to_string());
+            }
+        }
+        for arg in self.generic_args.iter() {
+            traits.extend(arg.unmapped_traits(opts));
+        }
+        traits
+    }
+
+    fn to_ts(&self, opts: &Options) -> String {
+        self.to_ts_from(opts, true)
+    }
+
+    /// Convert the type, where `local` is false for paths into other
+    /// crates, which can't refer to types defined in the input
+    fn to_ts_from(&self, opts: &Options, local: bool) -> String {
+        if self.trait_object {
+            // Matched by the trait's name without its module
+            let name = self.path.last().unwrap();
+            return match opts.trait_types.get(name) {
+                Some(ty) => ty.to_string(),
+                None => "unknown".to_string(),
+            };
+        }
+        if let Some(len) = &self.array_len {
+            let elem = self.generic_args[0].to_ts(opts);
+            match len.parse::<usize>() {
+                // Short arrays like coordinates are more useful as
+                // tuples
+                Ok(len) if len <= opts.max_tuple_len => {
+                    format!("[{}]", vec![elem; len].join(", "))
+                }
+                _ => array_of(elem),
+            }
+        } else if self.is_wrapper(opts) {
+            self.generic_args[0].to_ts(opts)
+        } else if local && self.is_defined(opts) {
+            // Checked before the well-known types, e.g. for a struct
+            // named Path
+            self.name_to_ts(opts)
+        } else if self.is_option() {
+            let inner = &self.generic_args[0];
+            if inner.is_option() {
+                // Both layers of None can't be told apart in the type
+                inner.to_ts(opts)
+            } else {
+                format!("{} | null", inner.to_ts(opts))
+            }
+        } else if self.is_sequence() {
+            array_of(self.generic_args[0].to_ts(opts))
+        } else if let Some(elem) = self.fixed_capacity_elem() {
+            array_of(elem.to_ts(opts))
+        } else if self.is_small_string(opts) {
+            "string".to_string()
+        } else if self.path == ["Result"] && self.generic_args.len() == 2 {
+            format!(
+                "{{ Ok: {} }} | {{ Err: {} }}",
+                self.generic_args[0].to_ts(opts),
+                self.generic_args[1].to_ts(opts)
+            )
+        } else if self.is_range() {
+            let bound = self.generic_args[0].to_ts(opts);
+            format!("{{ start: {}; end: {} }}", bound, bound)
+        } else if self.is_either() {
+            format!(
+                "{{ Left: {} }} | {{ Right: {} }}",
+                self.generic_args[0].to_ts(opts),
+                self.generic_args[1].to_ts(opts)
+            )
+        } else if self.is_json_type("Value") && self.generic_args.is_empty() {
+            opts.json_value.to_string()
+        } else if self.is_json_type("Map") && self.generic_args.len() == 2 {
+            format!(
+                "Record<{}, {}>",
+                self.generic_args[0].to_ts(opts),
+                self.generic_args[1].to_ts(opts)
+            )
+        } else if let Some(name) = self.time_type() {
+            match opts.time_types.get(name) {
+                Some(ty) => ty.to_string(),
+                None => "string".to_string(),
+            }
+        } else if self.is_chrono_duration() {
+            opts.chrono_duration.to_string()
+        } else if let Some(ty) = self.bson_type(opts) {
+            ty.to_string()
+        } else if let Some(ty) = self.std_time_type() {
+            ty.to_string()
+        } else if let Some(offset) = self.datetime_offset() {
+            // All offsets serialize as RFC 3339 strings
+            match opts.datetime_types.get(offset) {
+                Some(ty) => ty.to_string(),
+                None => opts.datetime_alias.0.to_string(),
+            }
+        } else if self.path.len() > 1 {
+            let (modules, bare, local) = self.split_path(opts);
+            match modules.first() {
+                Some(first) if opts.module_namespaces && opts.modules.contains(*first) => {
+                    let mut out = String::new();
+                    for m in modules {
+                        out += m;
+                        out += ".";
+                    }
+                    out + &bare.to_ts_from(opts, local)
+                }
+                _ => bare.to_ts_from(opts, local),
+            }
+        } else if self.is_map() {
+            format!(
+                "Record<{}, {}>",
+                self.generic_args[0].to_ts(opts),
+                self.generic_args[1].to_ts(opts)
+            )
+        } else if self.generic_args.is_empty() {
+            if NUMERIC_TYPES.contains(&self.path[0].as_str()) {
+                "number".to_string()
+            } else if INT64_TYPES.contains(&self.path[0].as_str()) {
+                opts.int64.to_ts().to_string()
+            } else if let Some((_, alias)) = NAIVE_DATE_TYPES
+                .iter()
+                .find(|(name, _)| self.path[0] == *name)
+            {
+                if opts.date_aliases {
+                    alias.to_string()
+                } else {
+                    "string".to_string()
+                }
+            } else if opts.bitflags.contains(&self.path[0]) {
+                opts.bitflags_type.to_string()
+            } else if DECIMAL_TYPES.contains(&self.path[0].as_str()) {
+                opts.decimal.to_string()
+            } else if BYTES_TYPES.contains(&self.path[0].as_str()) {
+                opts.bytes.to_string()
+            } else if INT128_TYPES.contains(&self.path[0].as_str()) {
+                opts.int128.to_ts().to_string()
+            } else if self.path[0] == "Uuid" && opts.uuid_alias {
+                "Uuid".to_string()
+            } else if self.path[0] == "Url" && opts.url_alias {
+                "Url".to_string()
+            } else if STRING_TYPES.contains(&self.path[0].as_str()) {
+                "string".to_string()
+            } else if self.path[0] == "()" {
+                opts.unit_type.to_string()
+            } else if self.path[0] == "bool" {
+                "boolean".to_string()
+            } else if let Some(int) = nonzero_int(&self.path[0]) {
+                SimpleType::new(vec![int], vec![]).to_ts(opts)
+            } else {
+                self.name_to_ts(opts)
+            }
+        } else {
+            self.name_to_ts(opts)
+        }
+    }
+
+    /// Collect the names of the types this type may be emitted as a
+    /// reference to, including aliases from the preamble
+    fn referenced_names(&self, opts: &Options, names: &mut HashSet<String>) {
+        self.referenced_names_from(opts, true, names)
+    }
+
+    fn referenced_names_from(&self, opts: &Options, local: bool, names: &mut HashSet<String>) {
+        if self.path.len() > 1 && !self.trait_object {
+            let (modules, bare, bare_local) = self.split_path(opts);
+            // Module names are namespaces with --module-namespaces
+            if let Some(first) = modules.first() {
+                if self
+                    .to_ts_from(opts, local)
+                    .starts_with(&format!("{}.", first))
+                {
+                    names.insert(first.to_string());
+                }
+            }
+            return bare.referenced_names_from(opts, bare_local, names);
+        }
+        if let Some(offset) = self.datetime_offset() {
+            if !opts.datetime_types.contains_key(offset) {
+                names.insert(opts.datetime_alias.0.to_string());
+            }
+        }
+        if let Some((_, alias)) = NAIVE_DATE_TYPES
+            .iter()
+            .find(|(name, _)| self.path.last().is_some_and(|n| n == name))
+        {
+            names.insert(alias.to_string());
+        }
+        // Only types that are emitted as their own name, not ones
+        // mapped to a built-in type like std::path::Path
+        if self.path.len() == 1
+            && !self.trait_object
+            && self.to_ts_from(opts, local) == self.name_to_ts(opts)
+        {
+            if opts.monomorphize && !self.generic_args.is_empty() {
+                // The arguments are part of the instantiation
+                names.insert(self.mono_name());
+                return;
+            }
+            names.insert(self.path[0].to_string());
+        }
+        for arg in self.generic_args.iter() {
+            arg.referenced_names(opts, names);
+        }
+    }
+
+    /// Check if the type contains an i64 or u64, which might not fit
+    /// in a JavaScript number
+    fn uses_int64(&self) -> bool {
+        let is_int64 = |name: &str| INT64_TYPES.contains(&name);
+        let name = self.path.last().map(String::as_str).unwrap_or("");
+        is_int64(name)
+            || nonzero_int(name).is_some_and(|int| is_int64(&int))
+            || self.generic_args.iter().any(|arg| arg.uses_int64())
+    }
+
+    /// Check if the type refers to a type defined in the input
+    fn is_defined(&self, opts: &Options) -> bool {
+        self.path.len() == 1
+            && opts.defined_types.contains(&self.path[0])
+            && !opts.string_types.contains(&self.path[0])
+    }
+
+    /// Get the type as a reference to a named type, with generic
+    /// arguments for generic types like Paginated<User>
+    fn name_to_ts(&self, opts: &Options) -> String {
+        if self.generic_args.is_empty() {
+            return self.path[0].to_string();
+        }
+        let args = self
+            .generic_args
+            .iter()
+            .map(|arg| arg.to_ts(opts))
+            .collect::<Vec<_>>();
+        if opts.monomorphize {
+            self.mono_name()
+        } else {
+            format!("{}<{}>", self.path[0], args.join(", "))
+        }
+    }
+
+    /// Split a qualified path into its modules and the bare type,
+    /// and check if the type can be defined in the input
+    fn split_path(&self, opts: &Options) -> (Vec<&String>, SimpleType, bool) {
+        // Qualified paths like std::collections::HashMap map the
+        // same as the bare name
+        let mut bare = self.clone();
+        bare.path.drain(..self.path.len() - 1);
+        // Types in inline modules are in namespaces of the same
+        // name. Relative paths like `super::X` resolve the same way
+        // in TypeScript.
+        let modules = self
+            .path
+            .iter()
+            .take(self.path.len() - 1)
+            .skip_while(|s| *s == "crate" || *s == "self" || *s == "super")
+            .collect::<Vec<_>>();
+        // Paths starting with crate, self, or super, or with an
+        // inline module, can refer to types defined in the input
+        let local = modules.len() + 1 < self.path.len()
+            || modules.first().is_some_and(|m| opts.modules.contains(*m));
+        (modules, bare, local)
+    }
+
+    /// Replace type parameters with concrete types
+    fn substitute(&self, args: &HashMap<String, SimpleType>) -> SimpleType {
+        if self.path.len() == 1 && self.generic_args.is_empty() && !self.trait_object {
+            if let Some(ty) = args.get(&self.path[0]) {
+                return ty.clone();
+            }
+        }
+        let mut st = self.clone();
+        st.generic_args = self
+            .generic_args
+            .iter()
+            .map(|arg| arg.substitute(args))
+            .collect();
+        st
+    }
+
+    /// Get the name of a generic type's instantiation for
+    /// --monomorphize, e.g. PaginatedUser for Paginated<User>
+    fn mono_name(&self) -> String {
+        let name = match self.path.last().map(String::as_str) {
+            None => "Array",
+            Some("()") => "Unit",
+            Some(name) => name,
+        };
+        let mut chars = name.chars();
+        let mut out = match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect(),
+            None => String::new(),
+        };
+        for arg in self.generic_args.iter() {
+            out += &arg.mono_name();
+        }
+        out
+    }
+
+    /// Get the names of types this type refers to without an object
+    /// or array in between. A type alias can't refer to itself like
+    /// that.
+    fn direct_refs(&self, opts: &Options) -> Vec<&str> {
+        if self.array_len.is_some() || self.trait_object {
+            Vec::new()
+        } else if self.is_wrapper(opts) || self.is_option() {
+            self.generic_args[0].direct_refs(opts)
+        } else if self.is_map() {
+            // Record is itself an alias
+            self.generic_args[1].direct_refs(opts)
+        } else if self.generic_args.is_empty() {
+            self.path.last().map(String::as_str).into_iter().collect()
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Get how deeply generic arguments are nested, e.g. 2 for
+    /// Vec<Vec<i32>>
+    fn depth(&self) -> usize {
+        1 + self
+            .generic_args
+            .iter()
+            .map(|a| a.depth())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Collect all the named types in this type, including itself,
+    /// which might be instantiations of generic types
+    fn named_uses(&self, uses: &mut Vec<SimpleType>) {
+        if !self.path.is_empty() {
+            uses.push(self.clone());
+        }
+        for arg in self.generic_args.iter() {
+            arg.named_uses(uses);
+        }
+    }
+}
+
+impl SimpleEnum {
+    fn from_syn_type(e: &syn::ItemEnum) -> Option<SimpleEnum> {
+        let name = ident_name(&e.ident);
+        let tagging = match (serde_str(&e.attrs, "tag"), serde_str(&e.attrs, "content")) {
+            (Some(tag), Some(content)) => EnumTagging::Adjacent { tag, content },
+            (Some(tag), None) => EnumTagging::Internal { tag },
+            _ if serde_flag(&e.attrs, "untagged") => EnumTagging::Untagged,
+            _ => EnumTagging::External,
+        };
+        let mut derives = Vec::new();
+        for attr in e.attrs.iter() {
+            derives.append(&mut attr_to_derives(attr));
+        }
+        // Without serde, strum's EnumString and Display are the
+        // likely string format, e.g. for query params
+        let strum = !derives_any(&derives, &["Serialize", "Deserialize"]);
+        let mut rename_all = serde_rename_all(&e.attrs);
+        if strum && rename_all.is_none() {
+            rename_all = attr_strs(&e.attrs, "strum", "serialize_all")
+                .first()
+                .and_then(|rule| parse_rename_rule(rule));
+        }
+        let mut se = SimpleEnum {
+            name,
+            variants: Vec::new(),
+            rename_all,
+            tagging,
+            repr: derives_any(&derives, &["Serialize_repr", "Deserialize_repr"]),
+            rename_all_fields: serde_str(&e.attrs, "rename_all_fields")
+                .and_then(|rule| parse_rename_rule(&rule)),
+            generics: type_params(&e.generics),
+            non_exhaustive: has_attr(&e.attrs, "non_exhaustive"),
+            doc: doc_lines(&e.attrs),
+        };
+        for v in e.variants.iter() {
+            let mut fields = Vec::new();
+            for f in v.fields.iter() {
+                match SimpleField::from_syn_field(f, false) {
+                    Ok(sf) => fields.push(sf),
+                    Err(err) => {
+                        eprintln!("{}::{}: {}", se.name, v.ident, err);
+                        return None;
+                    }
+                }
+            }
+            let is_tuple = matches!(v.fields, syn::Fields::Unnamed(_));
+            if is_tuple
+                && fields.len() > 1
+                && matches!(se.tagging, EnumTagging::Internal { .. })
+                && !serde_flag(&v.attrs, "untagged")
+            {
+                // serde rejects this at compile time too
+                eprintln!(
+                    "{}::{}: tuple variants can't be internally tagged",
+                    se.name, v.ident
+                );
+                return None;
+            }
+            let mut sv = SimpleVariant::new(ident_name(&v.ident), fields);
+            sv.named = matches!(v.fields, syn::Fields::Named(_));
+            sv.rename_all = serde_rename_all(&v.attrs);
+            sv.rename = serde_str(&v.attrs, "rename");
+            sv.aliases = serde_strs(&v.attrs, "alias");
+            if strum && sv.rename.is_none() {
+                strum_names(&v.attrs, &mut sv);
+            }
+            sv.other = serde_flag(&v.attrs, "other");
+            sv.untagged = serde_flag(&v.attrs, "untagged");
+            sv.doc = doc_lines(&v.attrs);
+            let skip = serde_flag(&v.attrs, "skip");
+            sv.skip_serializing = skip || serde_flag(&v.attrs, "skip_serializing");
+            sv.skip_deserializing = skip || serde_flag(&v.attrs, "skip_deserializing");
+            if let Some((_, expr)) = &v.discriminant {
+                sv.discriminant = expr_to_int(expr);
+                if sv.discriminant.is_none() {
+                    eprintln!("{}::{}: unsupported discriminant", se.name, v.ident);
+                }
+            }
+            se.variants.push(sv);
+        }
+        Some(se)
+    }
+
+    /// Get the value of each variant's discriminant, following the
+    /// same numbering as rustc for variants without one
+    fn discriminants(&self) -> Option<Vec<i64>> {
+        let mut next = Some(0);
+        let mut values = Vec::new();
+        for v in self.variants.iter() {
+            let value = match v.discriminant.or(next) {
+                Some(value) => value,
+                None => {
+                    eprintln!("{}::{}: discriminant overflows i64", self.name, v.name);
+                    return None;
+                }
+            };
+            values.push(value);
+            next = value.checked_add(1);
+        }
+        Some(values)
+    }
+
+    /// Get the name of a variant as it appears in the JSON
+    fn variant_name(&self, variant: &SimpleVariant) -> String {
+        if let Some(rename) = &variant.rename {
+            return rename.to_string();
+        }
+        match self.rename_all {
+            Some(rule) => rule.apply_to_variant(&variant.name),
+            None => variant.name.to_string(),
+        }
+    }
+
+    /// Get the name of a struct variant's field as it appears in the
+    /// JSON
+    fn variant_field_name(&self, variant: &SimpleVariant, field: &SimpleField) -> String {
+        if let Some(rename) = &field.rename {
+            return rename.to_string();
+        }
+        let name = field.name.as_ref().unwrap();
+        match variant.rename_all.or(self.rename_all_fields) {
+            Some(rule) => rule.apply_to_field(name),
+            None => name.to_string(),
+        }
+    }
+
+    /// Get the members of a struct variant, e.g. `x: number; y: number`
+    fn variant_members(&self, v: &SimpleVariant, opts: &Options) -> String {
+        let members = v
+            .fields
+            .iter()
+            .filter(|f| {
+                !opts
+                    .direction
+                    .skips(f.skip_serializing, f.skip_deserializing)
+            })
+            .map(|f| {
+                let name = self.variant_field_name(v, f);
+                let place = format!("{}::{}.{}", self.name, v.name, name);
+                format!(
+                    "{}{}: {}",
+                    property_name(&name),
+                    if f.is_optional(opts) { "?" } else { "" },
+                    f.member_to_ts(&place, opts)
+                )
+            })
+            .collect::<Vec<_>>();
+        members.join("; ")
+    }
+
+    /// Get the variant's payload: an object for struct variants, and
+    /// a tuple if there is more than one unnamed field
+    fn variant_content(&self, v: &SimpleVariant, opts: &Options) -> String {
+        if v.named {
+            format!("{{ {} }}", self.variant_members(v, opts))
+        } else {
+            let mut fields = v
+                .fields
+                .iter()
+                .enumerate()
+                .map(|(i, f)| f.to_ts(&format!("{}::{}.{}", self.name, v.name, i), opts))
+                .collect::<Vec<String>>();
+            if fields.len() == 1 {
+                return fields.remove(0);
+            }
+            format!("[{}]", fields.join(", "))
+        }
+    }
+
+    fn variant_to_ts(&self, v: &SimpleVariant, opts: &Options) -> String {
+        let name = self.variant_name(v);
+        let tagging = if v.untagged {
+            &EnumTagging::Untagged
+        } else {
+            &self.tagging
+        };
+        match tagging {
+            EnumTagging::External => {
+                if v.fields.is_empty() {
+                    format!("\"{}\"", name)
+                } else {
+                    format!(
+                        "{{ {}: {} }}",
+                        property_name(&name),
+                        self.variant_content(v, opts)
+                    )
+                }
+            }
+            EnumTagging::Untagged => {
+                if v.fields.is_empty() {
+                    // Unit variants serialize as null
+                    "null".to_string()
+                } else {
+                    self.variant_content(v, opts)
+                }
+            }
+            EnumTagging::Adjacent { tag, content } => {
+                if v.fields.is_empty() {
+                    format!("{{ {}: \"{}\" }}", property_name(tag), name)
+                } else {
+                    format!(
+                        "{{ {}: \"{}\"; {}: {} }}",
+                        property_name(tag),
+                        name,
+                        property_name(content),
+                        self.variant_content(v, opts)
+                    )
+                }
+            }
+            EnumTagging::Internal { tag } => {
+                if v.named {
+                    // The fields go next to the tag
+                    return format!(
+                        "{{ {}: \"{}\"; {} }}",
+                        property_name(tag),
+                        name,
+                        self.variant_members(v, opts)
+                    );
+                }
+                let tag = format!("{{ {}: \"{}\" }}", property_name(tag), name);
+                if v.fields.is_empty() {
+                    tag
+                } else {
+                    // The newtype's fields are merged in next to the tag
+                    format!("{} & {}", tag, self.variant_content(v, opts))
+                }
+            }
+        }
+    }
+
+    /// Get a const object with the discriminant of each variant, for
+    /// C-like enums that have explicit discriminants or serialize as
+    /// numbers
+    fn discriminants_to_ts(&self) -> Option<String> {
+        let c_like = self.variants.iter().all(|v| v.fields.is_empty());
+        let numeric = self.repr || self.variants.iter().any(|v| v.discriminant.is_some());
+        if !c_like || !numeric {
+            return None;
+        }
+        let discriminants = self.discriminants()?;
+        let mut out = format!("export const {} = {{\n", self.name);
+        for (v, d) in self.variants.iter().zip(discriminants) {
+            out += &format!("  {}: {},\n", v.name, d);
+        }
+        Some(out + "} as const;\n")
+    }
+
+    fn to_ts(&self, opts: &Options) -> String {
+        let mut notes = Vec::new();
+        if self.non_exhaustive {
+            notes.push("More variants may be added".to_string());
+        }
+        let doc = jsdoc(&doc_with_notes(&self.doc, notes), "");
+        let out = doc + &self.type_to_ts(opts);
+        if !opts.enum_consts {
+            return out;
+        }
+        match self.discriminants_to_ts() {
+            Some(consts) => out + &consts,
+            None => out,
+        }
+    }
+
+    fn type_to_ts(&self, opts: &Options) -> String {
+        let mut out = format!(
+            "export type {} =\n",
+            ts_name(&self.name, &self.generics, opts)
+        );
+        if opts.alias_cycles.contains(&self.name) {
+            return out + "  unknown;\n";
+        }
+        let included = |v: &&SimpleVariant| {
+            !opts
+                .direction
+                .skips(v.skip_serializing, v.skip_deserializing)
+        };
+        if self.repr {
+            let discriminants = match self.discriminants() {
+                Some(discriminants) => discriminants,
+                None => return out + "  number;\n",
+            };
+            let values = self
+                .variants
+                .iter()
+                .zip(discriminants)
+                .filter(|(v, _)| included(v))
+                .map(|(_, d)| format!("  {}", d))
+                .collect::<Vec<_>>();
+            return out + &values.join(" |\n") + ";\n";
+        }
+        let mut variants = Vec::new();
+        for v in self.variants.iter().filter(included) {
+            let notes = if opts.direction != Direction::Serialize {
+                alias_note(&v.aliases)
+            } else {
+                Vec::new()
+            };
+            let doc = jsdoc(&doc_with_notes(&v.doc, notes), "  ");
+            variants.push(format!("{}  {}", doc, self.variant_to_ts(v, opts)));
+        }
+        // Unknown variant names are accepted when deserializing
+        if opts.direction != Direction::Serialize && self.variants.iter().any(|v| v.other) {
+            let fallback = &opts.other_fallback;
+            match &self.tagging {
+                EnumTagging::External => variants.push(format!("  {}", fallback)),
+                EnumTagging::Internal { tag } | EnumTagging::Adjacent { tag, .. } => {
+                    variants.push(format!("  {{ {}: {} }}", property_name(tag), fallback))
+                }
+                EnumTagging::Untagged => {}
+            }
+        }
+        if let (true, Some(fallback)) = (self.non_exhaustive, &opts.non_exhaustive_fallback) {
+            variants.push(format!("  {}", fallback));
+        }
+        out += &variants.join(" |\n");
+        out += ";\n";
+        out
+    }
+}
+
+/// Get the value of an integer literal expression, which may be
+/// negated
+fn expr_to_int(expr: &syn::Expr) -> Option<i64> {
+    // Work in i128 so that `-9223372036854775808` doesn't overflow
+    // before the negation is applied
+    fn wide(expr: &syn::Expr) -> Option<i128> {
+        match expr {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Int(i),
+                ..
+            }) => Some(i128::from(i.value())),
+            syn::Expr::Unary(syn::ExprUnary {
+                op: syn::UnOp::Neg(_),
+                expr,
+                ..
+            }) => wide(expr)?.checked_neg(),
+            _ => None,
+        }
+    }
+    i64::try_from(wide(expr)?).ok()
+}
+
+/// A flags type declared in a `bitflags! { ... }` macro
+struct Bitflags {
     name: String,
-    enums: Vec<SimpleEnum>,
-    structs: Vec<SimpleStruct>,
+    flags: Vec<(String, syn::Expr)>,
 }
 
-impl SimpleFile {
-    fn load(path: &std::path::Path) -> SimpleFile {
-        let src = fs::read_to_string(path).expect("Unable to read file");
+/// The contents of a `bitflags!` macro, which can declare several
+/// flags types
+struct BitflagsMacro {
+    types: Vec<Bitflags>,
+}
+
+impl syn::parse::Parse for BitflagsMacro {
+    fn parse(input: syn::parse::ParseStream) -> syn::parse::Result<BitflagsMacro> {
+        let mut types = Vec::new();
+        while !input.is_empty() {
+            input.call(syn::Attribute::parse_outer)?;
+            input.parse::<syn::Visibility>()?;
+            input.parse::<syn::Token![struct]>()?;
+            let name = ident_name(&input.parse::<syn::Ident>()?);
+            input.parse::<syn::Token![:]>()?;
+            input.parse::<syn::Type>()?;
+            let content;
+            syn::braced!(content in input);
+            let mut flags = Vec::new();
+            while !content.is_empty() {
+                content.call(syn::Attribute::parse_outer)?;
+                content.parse::<syn::Token![const]>()?;
+                // `const _ = ...` marks extra known bits without
+                // naming a flag
+                let name = if content.peek(syn::Token![_]) {
+                    content.parse::<syn::Token![_]>()?;
+                    None
+                } else {
+                    Some(ident_name(&content.parse::<syn::Ident>()?))
+                };
+                content.parse::<syn::Token![=]>()?;
+                let value = content.parse::<syn::Expr>()?;
+                content.parse::<syn::Token![;]>()?;
+                if let Some(name) = name {
+                    flags.push((name, value));
+                }
+            }
+            types.push(Bitflags { name, flags });
+        }
+        Ok(BitflagsMacro { types })
+    }
+}
+
+impl Bitflags {
+    /// Evaluate a flag's value, which may refer to earlier flags
+    fn eval(expr: &syn::Expr, known: &[(String, i64)]) -> Option<i64> {
+        match expr {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Int(i),
+                ..
+            }) => Some(i.value() as i64),
+            syn::Expr::Paren(p) => Bitflags::eval(&p.expr, known),
+            syn::Expr::Binary(b) => {
+                let left = Bitflags::eval(&b.left, known)?;
+                let right = Bitflags::eval(&b.right, known)?;
+                match b.op {
+                    syn::BinOp::Shl(_) => left.checked_shl(right as u32),
+                    syn::BinOp::BitOr(_) => Some(left | right),
+                    syn::BinOp::BitAnd(_) => Some(left & right),
+                    syn::BinOp::BitXor(_) => Some(left ^ right),
+                    syn::BinOp::Add(_) => left.checked_add(right),
+                    syn::BinOp::Sub(_) => left.checked_sub(right),
+                    syn::BinOp::Mul(_) => left.checked_mul(right),
+                    _ => None,
+                }
+            }
+            // `Self::A`, `Flags::A`, or just `A`
+            syn::Expr::Path(p) => {
+                let name = p.path.segments.iter().last()?.ident.to_string();
+                known.iter().find(|(n, _)| *n == name).map(|(_, v)| *v)
+            }
+            // `Self::A.bits()` or `Self::A.bits`
+            syn::Expr::MethodCall(m) if m.method == "bits" && m.args.is_empty() => {
+                Bitflags::eval(&m.receiver, known)
+            }
+            syn::Expr::Field(syn::ExprField {
+                base,
+                member: syn::Member::Named(member),
+                ..
+            }) if member == "bits" => Bitflags::eval(base, known),
+            _ => None,
+        }
+    }
+
+    /// Get a const object with the value of each flag
+    fn to_ts(&self) -> String {
+        let mut values: Vec<(String, i64)> = Vec::new();
+        for (name, expr) in self.flags.iter() {
+            match Bitflags::eval(expr, &values) {
+                Some(value) => values.push((name.to_string(), value)),
+                None => eprintln!("{}::{}: unable to evaluate flag value", self.name, name),
+            }
+        }
+        let mut out = format!("export const {} = {{\n", self.name);
+        for (name, value) in values {
+            out += &format!("  {}: {},\n", name, value);
+        }
+        out + "} as const;\n"
+    }
+}
+
+/// The contents of `#[cfg_attr(condition, attr1, attr2, ...)]`
+struct CfgAttr {
+    attrs: Vec<syn::Attribute>,
+}
+
+impl syn::parse::Parse for CfgAttr {
+    fn parse(input: syn::parse::ParseStream) -> syn::parse::Result<CfgAttr> {
+        let content;
+        syn::parenthesized!(content in input);
+        content.parse::<syn::NestedMeta>()?;
+        let mut attrs = Vec::new();
+        while !content.is_empty() {
+            content.parse::<syn::Token![,]>()?;
+            if content.is_empty() {
+                break;
+            }
+            let path = content.call(syn::Path::parse_mod_style)?;
+            let mut tts = proc_macro2::TokenStream::new();
+            while !content.is_empty() && !content.peek(syn::Token![,]) {
+                tts.extend(Some(content.parse::<proc_macro2::TokenTree>()?));
+            }
+            attrs.push(syn::Attribute {
+                pound_token: Default::default(),
+                style: syn::AttrStyle::Outer,
+                bracket_token: Default::default(),
+                path,
+                tts,
+            });
+        }
+        Ok(CfgAttr { attrs })
+    }
+}
+
+/// Get the attributes inside a `cfg_attr` as if the condition were
+/// true, since there's no way to know which features the JSON is
+/// produced with. Other attributes are returned as is.
+fn expand_cfg_attr(attr: &syn::Attribute) -> Vec<syn::Attribute> {
+    if !attr.path.is_ident("cfg_attr") {
+        return vec![attr.clone()];
+    }
+    match syn::parse2::<CfgAttr>(attr.tts.clone()) {
+        Ok(cfg_attr) => cfg_attr.attrs.iter().flat_map(expand_cfg_attr).collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Parse an attribute, looking inside `cfg_attr`
+fn attr_metas(attr: &syn::Attribute) -> Vec<syn::Meta> {
+    expand_cfg_attr(attr)
+        .iter()
+        .filter_map(|attr| attr.parse_meta().ok())
+        .collect()
+}
+
+/// The contents of `#[derive(...)]`. Derives can be paths like
+/// `serde::Serialize`, which syn::Meta doesn't allow.
+struct DeriveList {
+    paths: Vec<syn::Path>,
+}
+
+impl syn::parse::Parse for DeriveList {
+    fn parse(input: syn::parse::ParseStream) -> syn::parse::Result<DeriveList> {
+        let content;
+        syn::parenthesized!(content in input);
+        let paths = content.parse_terminated::<_, syn::Token![,]>(syn::Path::parse_mod_style)?;
+        Ok(DeriveList {
+            paths: paths.into_iter().collect(),
+        })
+    }
+}
+
+fn attr_to_derives(attr: &syn::Attribute) -> Vec<String> {
+    let mut derives = Vec::new();
+    for attr in expand_cfg_attr(attr) {
+        if !attr.path.is_ident("derive") {
+            continue;
+        }
+        if let Ok(lst) = syn::parse2::<DeriveList>(attr.tts) {
+            for path in lst.paths {
+                let segments = path
+                    .segments
+                    .iter()
+                    .map(|seg| ident_name(&seg.ident))
+                    .collect::<Vec<_>>();
+                derives.push(segments.join("::"));
+            }
+        }
+    }
+    derives
+}
+
+/// Check if any of the derives match one of the names. Paths match
+/// by their last segment, so `serde::Serialize` matches `Serialize`.
+fn derives_any(derives: &[String], names: &[&str]) -> bool {
+    derives.iter().any(|d| {
+        let last = d.rsplit("::").next().unwrap();
+        names.contains(&d.as_str()) || names.contains(&last)
+    })
+}
+
+/// Get the items inside all `#[name(...)]` attributes, e.g. the
+/// `rename_all = "camelCase"` in `#[serde(rename_all = "camelCase")]`
+fn attr_items(attrs: &[syn::Attribute], name: &str) -> Vec<syn::Meta> {
+    let mut items = Vec::new();
+    for meta in attrs.iter().flat_map(attr_metas) {
+        if let syn::Meta::List(lst) = meta {
+            if lst.ident != name {
+                continue;
+            }
+            for child in lst.nested.into_iter() {
+                if let syn::NestedMeta::Meta(meta) = child {
+                    items.push(meta);
+                }
+            }
+        }
+    }
+    items
+}
+
+/// Get the values of all `#[name(key = "value")]` attributes
+fn attr_strs(attrs: &[syn::Attribute], name: &str, key: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    for meta in attr_items(attrs, name) {
+        if let syn::Meta::NameValue(nv) = meta {
+            if nv.ident == key {
+                if let syn::Lit::Str(s) = nv.lit {
+                    values.push(s.value());
+                }
+            }
+        }
+    }
+    values
+}
+
+/// Get the values of all `#[serde(key = "value")]` attributes
+fn serde_strs(attrs: &[syn::Attribute], key: &str) -> Vec<String> {
+    attr_strs(attrs, "serde", key)
+}
+
+/// Get the value of a `#[serde(key = "value")]` attribute
+fn serde_str(attrs: &[syn::Attribute], key: &str) -> Option<String> {
+    serde_strs(attrs, key).into_iter().next()
+}
+
+/// Format lines of text as a JSDoc comment
+fn jsdoc(lines: &[String], indent: &str) -> String {
+    if lines.is_empty() {
+        String::new()
+    } else if lines.len() == 1 {
+        format!("{}/** {} */\n", indent, lines[0])
+    } else {
+        let mut out = format!("{}/**\n", indent);
+        for line in lines.iter() {
+            out += &format!("{} * {}\n", indent, line).replace(" * \n", " *\n");
+        }
+        out + indent + " */\n"
+    }
+}
+
+/// Get a note listing `#[serde(alias = "...")]` names
+fn alias_note(aliases: &[String]) -> Vec<String> {
+    if aliases.is_empty() {
+        return Vec::new();
+    }
+    let quoted = aliases
+        .iter()
+        .map(|a| format!("\"{}\"", a))
+        .collect::<Vec<_>>();
+    vec![format!("Also accepted as {}", quoted.join(", "))]
+}
+
+/// Get the lines of a doc comment, i.e. `///` or `#[doc = "..."]`
+/// attributes
+fn doc_lines(attrs: &[syn::Attribute]) -> Vec<String> {
+    let mut lines = Vec::new();
+    for attr in attrs.iter() {
+        if let Ok(syn::Meta::NameValue(nv)) = attr.parse_meta() {
+            if let (true, syn::Lit::Str(s)) = (nv.ident == "doc", &nv.lit) {
+                let value = s.value();
+                // The attribute of a block comment has the whole
+                // comment, which can span several lines
+                let (text, block) =
+                    match value.strip_prefix("/**").and_then(|v| v.strip_suffix("*/")) {
+                        Some(text) => (text, true),
+                        None => (value.as_str(), false),
+                    };
+                for line in text.split('\n') {
+                    let line = match (block, line.trim_start().strip_prefix('*')) {
+                        (true, Some(rest)) => rest,
+                        _ => line,
+                    };
+                    let line = line.strip_prefix(' ').unwrap_or(line).trim_end();
+                    // Don't end the JSDoc comment early
+                    lines.push(line.replace("*/", "*\\/"));
+                }
+            }
+        }
+    }
+    while lines.first().is_some_and(|l| l.is_empty()) {
+        lines.remove(0);
+    }
+    while lines.last().is_some_and(|l| l.is_empty()) {
+        lines.pop();
+    }
+    lines
+}
+
+/// Get the lines of a JSDoc comment with a doc comment followed by
+/// notes from the attributes
+fn doc_with_notes(doc: &[String], notes: Vec<String>) -> Vec<String> {
+    let mut lines = doc.to_vec();
+    if !lines.is_empty() && !notes.is_empty() {
+        lines.push(String::new());
+    }
+    lines.extend(notes);
+    lines
+}
+
+/// Name a variant from `#[strum(to_string = "...")]` or
+/// `#[strum(serialize = "...")]`. Like strum's Display, the longest
+/// `serialize` is used when there are several; the others can still
+/// be parsed.
+fn strum_names(attrs: &[syn::Attribute], variant: &mut SimpleVariant) {
+    let mut names = attr_strs(attrs, "strum", "serialize");
+    let to_string = attr_strs(attrs, "strum", "to_string").into_iter().next();
+    let name = match to_string {
+        Some(name) => name,
+        None => match names.iter().enumerate().max_by_key(|(_, n)| n.len()) {
+            Some((i, _)) => names.remove(i),
+            None => return,
+        },
+    };
+    names.retain(|n| *n != name);
+    variant.rename = Some(name);
+    variant.aliases.extend(names);
+}
+
+/// Check for a `#[serde(key)]` attribute
+fn serde_flag(attrs: &[syn::Attribute], key: &str) -> bool {
+    attr_items(attrs, "serde").iter().any(|meta| {
+        if let syn::Meta::Word(ident) = meta {
+            ident == key
+        } else {
+            false
+        }
+    })
+}
+
+/// Check for either `#[serde(default)]` or `#[serde(default = "...")]`
+fn serde_default(attrs: &[syn::Attribute]) -> bool {
+    serde_flag(attrs, "default") || serde_str(attrs, "default").is_some()
+}
+
+fn serde_rename_all(attrs: &[syn::Attribute]) -> Option<RenameRule> {
+    parse_rename_rule(&serde_str(attrs, "rename_all")?)
+}
+
+fn parse_rename_rule(rule: &str) -> Option<RenameRule> {
+    let parsed = RenameRule::parse(rule);
+    if parsed.is_none() {
+        eprintln!("unknown rename rule: {}", rule);
+    }
+    parsed
+}
+
+impl SimpleStruct {
+    fn new(s: &syn::ItemStruct) -> Option<SimpleStruct> {
+        let name = ident_name(&s.ident);
+        let mut ss = SimpleStruct {
+            name,
+            fields: Vec::new(),
+            rename_all: serde_rename_all(&s.attrs),
+            transparent: serde_flag(&s.attrs, "transparent"),
+            deny_unknown_fields: serde_flag(&s.attrs, "deny_unknown_fields"),
+            unit: matches!(s.fields, syn::Fields::Unit),
+            non_exhaustive: has_attr(&s.attrs, "non_exhaustive"),
+            generics: type_params(&s.generics),
+            doc: doc_lines(&s.attrs),
+        };
+        let default_all = serde_default(&s.attrs);
+        for (i, field) in s.fields.iter().enumerate() {
+            let name = field.ident.as_ref().map(ident_name);
+            match SimpleField::from_syn_field(field, default_all) {
+                // Zero-sized markers carry no data
+                Ok(ref sf) if sf.ty.is_phantom_data() => {}
+                Ok(sf) => ss.fields.push(sf),
+                Err(err) => {
+                    eprintln!(
+                        "{}.{}: {}",
+                        ss.name,
+                        name.unwrap_or_else(|| i.to_string()),
+                        err
+                    );
+                }
+            }
+        }
+        Some(ss)
+    }
+
+    /// Get the name of a field as it appears in the JSON
+    fn field_name(&self, field: &SimpleField) -> String {
+        if let Some(rename) = &field.rename {
+            return rename.to_string();
+        }
+        let name = field.name.as_ref().unwrap();
+        match self.rename_all {
+            Some(rule) => rule.apply_to_field(name),
+            None => name.to_string(),
+        }
+    }
+
+    /// Get the interface line for a field, or None if the field
+    /// doesn't appear in the JSON
+    fn field_to_ts(&self, f: &SimpleField, opts: &Options) -> Option<String> {
+        if opts
+            .direction
+            .skips(f.skip_serializing, f.skip_deserializing)
+        {
+            return None;
+        }
+        let ty = f.member_to_ts(&format!("{}.{}", self.name, self.field_name(f)), opts);
+        // Aliases are only accepted when deserializing
+        let notes = if opts.direction != Direction::Serialize {
+            alias_note(&f.aliases)
+        } else {
+            Vec::new()
+        };
+        let doc = jsdoc(&doc_with_notes(&f.doc, notes), "  ");
+        Some(format!(
+            "{}  {}{}: {};\n",
+            doc,
+            property_name(&self.field_name(f)),
+            if f.is_optional(opts) { "?" } else { "" },
+            ty
+        ))
+    }
+
+    /// Get the field of a newtype or transparent struct, which is
+    /// serialized as just the field's value
+    fn newtype_field(&self) -> Option<&SimpleField> {
+        if self.transparent {
+            // Any other fields have to be skipped
+            self.fields
+                .iter()
+                .find(|f| !(f.skip_serializing && f.skip_deserializing))
+        } else if self.fields.len() == 1 && self.fields[0].name.is_none() {
+            Some(&self.fields[0])
+        } else {
+            None
+        }
+    }
+
+    /// Check if this is a tuple struct with more than one field,
+    /// which is serialized as an array
+    fn is_tuple(&self) -> bool {
+        !self.transparent && self.fields.len() > 1 && self.fields.iter().all(|f| f.name.is_none())
+    }
+
+    /// Check if the struct is emitted as an interface rather than a
+    /// type alias
+    fn is_interface(&self) -> bool {
+        !self.unit && !self.fields.is_empty() && !self.is_tuple() && self.newtype_field().is_none()
+    }
+
+    fn to_ts(&self, opts: &Options) -> String {
+        let doc = jsdoc(&self.doc, "");
+        if self.unit {
+            // serde_json writes unit structs as null
+            format!("{}export type {} = {};\n", doc, self.name, opts.unit_type)
+        } else if self.fields.is_empty() {
+            // `{}` as an interface would accept any object
+            format!(
+                "{}export type {} = Record<string, never>;\n",
+                doc,
+                ts_name(&self.name, &self.generics, opts)
+            )
+        } else if self.is_tuple() {
+            // Skipped fields are left out of the array
+            let elems = self
+                .fields
+                .iter()
+                .enumerate()
+                .filter(|(_, f)| {
+                    !opts
+                        .direction
+                        .skips(f.skip_serializing, f.skip_deserializing)
+                })
+                .map(|(i, f)| f.to_ts(&format!("{}.{}", self.name, i), opts))
+                .collect::<Vec<_>>();
+            format!(
+                "{}export type {} = [{}];\n",
+                doc,
+                ts_name(&self.name, &self.generics, opts),
+                elems.join(", ")
+            )
+        } else if let Some(field) = self.newtype_field() {
+            let ty = if opts.alias_cycles.contains(&self.name) {
+                "unknown".to_string()
+            } else {
+                let name = field.name.as_ref().map_or("0", String::as_str);
+                field.to_ts(&format!("{}.{}", self.name, name), opts)
+            };
+            format!(
+                "{}export type {} = {};\n",
+                doc,
+                ts_name(&self.name, &self.generics, opts),
+                ty
+            )
+        } else {
+            let flattened = self
+                .fields
+                .iter()
+                .filter(|f| f.flatten && !f.ty.is_map())
+                .map(|f| &f.ty)
+                .collect::<Vec<_>>();
+            let mut body = "{\n".to_string();
+            for f in self.fields.iter().filter(|f| !f.flatten) {
+                if let Some(line) = self.field_to_ts(f, opts) {
+                    body += &line;
+                }
+            }
+            // A flattened map collects all the keys that aren't
+            // otherwise used. The value has to be unknown since the
+            // other properties must match the index signature too.
+            if self.fields.iter().any(|f| f.flatten && f.ty.is_map()) {
+                body += "  [key: string]: unknown;\n";
+            }
+            body += "}";
+
+            let mut notes = Vec::new();
+            // Only matters for JSON sent to Rust
+            if self.deny_unknown_fields && opts.direction != Direction::Serialize {
+                notes.push("Unknown fields are rejected".to_string());
+            }
+            if self.non_exhaustive {
+                notes.push("More fields may be added".to_string());
+            }
+            let doc = jsdoc(&doc_with_notes(&self.doc, notes), "");
+
+            // Flattened structs can be inherited from, anything else
+            // has to be an intersection
+            if flattened
+                .iter()
+                .all(|ty| ty.generic_args.is_empty() && opts.interfaces.contains(&ty.to_ts(opts)))
+            {
+                let mut out = format!(
+                    "{}export interface {} ",
+                    doc,
+                    ts_name(&self.name, &self.generics, opts)
+                );
+                if !flattened.is_empty() {
+                    let names = flattened
+                        .iter()
+                        .map(|ty| ty.to_ts(opts))
+                        .collect::<Vec<_>>();
+                    out += &format!("extends {} ", names.join(", "));
+                }
+                out + &body + "\n"
+            } else {
+                let mut types = flattened
+                    .iter()
+                    .map(|ty| ty.to_ts(opts))
+                    .collect::<Vec<_>>();
+                if body != "{\n}" {
+                    types.push(body);
+                }
+                format!(
+                    "{}export type {} = {};\n",
+                    doc,
+                    ts_name(&self.name, &self.generics, opts),
+                    types.join(" & ")
+                )
+            }
+        }
+    }
+}
+
+struct SimpleFile {
+    name: String,
+    enums: Vec<SimpleEnum>,
+    structs: Vec<SimpleStruct>,
+    bitflags: Vec<Bitflags>,
+    aliases: Vec<SimpleAlias>,
+    // Only loaded with --include-consts or --include-statics
+    consts: Vec<SimpleConst>,
+    // Items that can't be converted but have a type given with
+    // #[rsts(type = "...")], by name
+    overrides: Vec<(String, String)>,
+    // Inline modules, with the module name in place of the file name
+    modules: Vec<SimpleFile>,
+}
+
+/// Check if the attributes include a plain attribute like
+/// `#[non_exhaustive]`
+fn has_attr(attrs: &[syn::Attribute], name: &str) -> bool {
+    attrs
+        .iter()
+        .any(|attr| attr.path.segments.len() == 1 && attr.path.segments[0].ident == name)
+}
+
+/// Check if the attributes include `#[cfg(test)]`
+fn is_cfg_test(attrs: &[syn::Attribute]) -> bool {
+    has_nested_word(attrs, "cfg", "test")
+}
+
+/// Check if the attributes include `#[<list>(<word>)]`
+fn has_nested_word(attrs: &[syn::Attribute], list: &str, word: &str) -> bool {
+    attrs.iter().any(|attr| match attr.parse_meta() {
+        Ok(syn::Meta::List(lst)) => {
+            lst.ident == list
+                && lst.nested.iter().any(|n| match n {
+                    syn::NestedMeta::Meta(syn::Meta::Word(w)) => w == word,
+                    _ => false,
+                })
+        }
+        _ => false,
+    })
+}
+
+type ItemHeader<'a> = (&'a [syn::Attribute], &'a syn::Visibility, &'a syn::Ident);
+
+/// Get the attributes, visibility, and name of items that can be
+/// excluded
+fn item_header(item: &syn::Item) -> Option<ItemHeader<'_>> {
+    match item {
+        syn::Item::Enum(i) => Some((&i.attrs, &i.vis, &i.ident)),
+        syn::Item::Struct(i) => Some((&i.attrs, &i.vis, &i.ident)),
+        syn::Item::Union(i) => Some((&i.attrs, &i.vis, &i.ident)),
+        syn::Item::Type(i) => Some((&i.attrs, &i.vis, &i.ident)),
+        syn::Item::Const(i) => Some((&i.attrs, &i.vis, &i.ident)),
+        syn::Item::Static(i) => Some((&i.attrs, &i.vis, &i.ident)),
+        syn::Item::Mod(i) => Some((&i.attrs, &i.vis, &i.ident)),
+        _ => None,
+    }
+}
+
+/// Check if an item is visible enough to be emitted
+fn is_visible(vis: &syn::Visibility, opts: &Options) -> bool {
+    match vis {
+        _ if !opts.only_pub => true,
+        syn::Visibility::Public(_) => true,
+        // `crate` on its own is the unstable shorthand for pub(crate)
+        syn::Visibility::Crate(_) => opts.pub_crate,
+        syn::Visibility::Restricted(r) => {
+            opts.pub_crate && r.in_token.is_none() && r.path.is_ident("crate")
+        }
+        syn::Visibility::Inherited => false,
+    }
+}
+
+/// Check if an item is left out of the output, either because it's
+/// internal (`#[doc(hidden)]` or private with `only_pub`) or
+/// excluded by the options
+fn is_excluded(item: &syn::Item, opts: &Options) -> bool {
+    let (attrs, vis, ident) = match item_header(item) {
+        Some(header) => header,
+        None => return false,
+    };
+    !is_visible(vis, opts)
+        || (!opts.include_hidden && has_nested_word(attrs, "doc", "hidden"))
+        || opts.exclude_attrs.iter().any(|name| has_attr(attrs, name))
+        || opts.exclude.contains(&ident_name(ident))
+}
+
+/// Wrap generated TypeScript in `export namespace name { ... }`
+fn namespace(name: &str, ts: &str) -> String {
+    format!("export namespace {} {{\n{}}}\n", name, indent(ts))
+}
+
+/// Wrap generated TypeScript in `declare module "name" { ... }`,
+/// which has to be converted with `to_declarations` since it's
+/// ambient
+fn declare_module(name: &str, ts: &str) -> String {
+    format!("declare module {:?} {{\n{}}}\n", name, indent(ts))
+}
+
+/// Indent each non-empty line by two spaces
+fn indent(s: &str) -> String {
+    s.lines()
+        .map(|line| {
+            if line.is_empty() {
+                "\n".to_string()
+            } else {
+                format!("  {}\n", line)
+            }
+        })
+        .collect()
+}
+
+/// Check if the attributes derive Serialize or Deserialize, or one
+/// of the configured aliases
+fn derives_serde(attrs: &[syn::Attribute], opts: &Options) -> bool {
+    let mut derives = Vec::new();
+    for attr in attrs.iter() {
+        derives.append(&mut attr_to_derives(attr));
+    }
+    let mut names = vec!["Serialize", "Deserialize"];
+    names.extend(opts.derive_aliases.iter().map(|a| a.as_str()));
+    derives_any(&derives, &names)
+}
+
+impl SimpleFile {
+    /// Panics if the source can't be parsed. That includes const
+    /// generic declarations like `struct M<const R: usize>`, which
+    /// syn 0.15 predates; uses like `Matrix<f64, 3>` are fine.
+    fn parse(name: String, src: &str, opts: &Options) -> SimpleFile {
+        let syntax = syn::parse_file(src).expect("Unable to parse file");
+        SimpleFile::from_items(name, syntax.items, opts)
+    }
+
+    fn from_items(name: String, items: Vec<syn::Item>, opts: &Options) -> SimpleFile {
+        let mut enums = Vec::new();
+        let mut structs = Vec::new();
+        let mut bitflags = Vec::new();
+        let mut overrides = Vec::new();
+        let mut aliases = Vec::new();
+        let mut consts = Vec::new();
+        let mut modules = Vec::new();
+
+        for item in items {
+            if is_excluded(&item, opts) {
+                continue;
+            }
+            if let syn::Item::Enum(s) = item {
+                if let Some(s) = SimpleEnum::from_syn_type(&s) {
+                    enums.push(s);
+                }
+            } else if let syn::Item::Struct(s) = item {
+                // Skip structs that don't derive Deserialize or
+                // Serialize. These traits might be manually
+                // implemented, but then it's not clear if we can
+                // meaningfully autogenerate a TypeScript type.
+                if !derives_serde(&s.attrs, opts) {
+                    continue;
+                }
+                if let Some(s) = SimpleStruct::new(&s) {
+                    structs.push(s);
+                }
+            } else if let syn::Item::Const(c) = item {
+                if opts.include_consts {
+                    consts.extend(SimpleConst::new(&c.ident, &c.expr));
+                }
+            } else if let syn::Item::Static(s) = item {
+                // A mutable static's value can change at runtime
+                if opts.include_statics && s.mutability.is_none() {
+                    consts.extend(SimpleConst::new(&s.ident, &s.expr));
+                }
+            } else if let syn::Item::Type(t) = item {
+                if let Some(a) = SimpleAlias::new(&t) {
+                    aliases.push(a);
+                }
+            } else if let syn::Item::Mod(m) = item {
+                // Modules in other files aren't followed. Test
+                // modules aren't part of the API.
+                if let (Some((_, items)), false) = (m.content, is_cfg_test(&m.attrs)) {
+                    modules.push(SimpleFile::from_items(ident_name(&m.ident), items, opts));
+                }
+            } else if let syn::Item::Union(u) = item {
+                let name = ident_name(&u.ident);
+                match attr_strs(&u.attrs, "rsts", "type").into_iter().next() {
+                    Some(ty) => overrides.push((name, ty)),
+                    None => eprintln!(
+                        "{}: unions have no serde representation, the type can be set \
+                         with #[rsts(type = \"...\")]",
+                        name
+                    ),
+                }
+            } else if let syn::Item::Macro(m) = item {
+                let is_bitflags = m
+                    .mac
+                    .path
+                    .segments
+                    .iter()
+                    .last()
+                    .is_some_and(|s| s.ident == "bitflags");
+                if is_bitflags {
+                    match syn::parse2::<BitflagsMacro>(m.mac.tts) {
+                        Ok(mac) => bitflags.extend(mac.types),
+                        Err(err) => eprintln!("bitflags!: {}", err),
+                    }
+                }
+            }
+        }
+
+        SimpleFile {
+            name,
+            enums,
+            structs,
+            bitflags,
+            aliases,
+            consts,
+            overrides,
+            modules,
+        }
+    }
+
+    /// Get the types of this file's fields and aliases, not
+    /// including its modules. Fields with a type override are left
+    /// out.
+    fn types(&self) -> Vec<&SimpleType> {
+        let fields = self
+            .structs
+            .iter()
+            .flat_map(|s| s.fields.iter())
+            .chain(
+                self.enums
+                    .iter()
+                    .flat_map(|e| e.variants.iter())
+                    .flat_map(|v| v.fields.iter()),
+            )
+            .filter(|f| f.type_override.is_none())
+            .map(|f| &f.ty);
+        fields.chain(self.aliases.iter().map(|a| &a.ty)).collect()
+    }
+
+    /// Get the names of the types this file defines at the top
+    /// level of its output
+    fn defined_names(&self, opts: &Options) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .overrides
+            .iter()
+            .map(|(name, _)| name.to_string())
+            .collect();
+        // Generic types are replaced by their instantiations when
+        // monomorphizing
+        for a in self.aliases.iter() {
+            if !opts.monomorphize || a.generics.is_empty() {
+                names.push(a.name.to_string());
+            }
+        }
+        for e in self.enums.iter() {
+            if !opts.monomorphize || e.generics.is_empty() {
+                names.push(e.name.to_string());
+            }
+        }
+        for s in self.structs.iter() {
+            if !opts.monomorphize || s.generics.is_empty() {
+                names.push(s.name.to_string());
+            }
+        }
+        for m in self.modules.iter() {
+            if opts.module_namespaces {
+                names.push(m.name.to_string());
+            } else {
+                names.extend(m.defined_names(opts));
+            }
+        }
+        names
+    }
+
+    /// Get the names of the types this file and its modules refer to
+    fn referenced_names(&self, opts: &Options) -> HashSet<String> {
+        let mut names = HashSet::new();
+        for f in self.all_files() {
+            let defaults = f
+                .structs
+                .iter()
+                .flat_map(|s| s.generics.iter())
+                .chain(f.enums.iter().flat_map(|e| e.generics.iter()))
+                .chain(f.aliases.iter().flat_map(|a| a.generics.iter()))
+                .filter_map(|p| p.default.as_ref());
+            for ty in f.types().into_iter().chain(defaults) {
+                ty.referenced_names(opts, &mut names);
+            }
+            // Types written out by hand can name other types too
+            let overrides = f
+                .structs
+                .iter()
+                .flat_map(|s| s.fields.iter())
+                .chain(
+                    f.enums
+                        .iter()
+                        .flat_map(|e| e.variants.iter())
+                        .flat_map(|v| v.fields.iter()),
+                )
+                .filter_map(|f| f.type_override.as_ref())
+                .chain(f.overrides.iter().map(|(_, ty)| ty));
+            for ty in overrides {
+                names.extend(ts_identifiers(ty).into_iter().map(str::to_string));
+            }
+        }
+        names
+    }
+
+    /// Get this file and all the modules in it
+    fn all_files(&self) -> Vec<&SimpleFile> {
+        let mut files = vec![self];
+        for m in self.modules.iter() {
+            files.extend(m.all_files());
+        }
+        files
+    }
+
+    /// Add the names of this file's types, interfaces, and modules
+    /// to the options
+    fn register_interfaces(&self, opts: &mut Options) {
+        for s in self.structs.iter() {
+            if s.is_interface() {
+                opts.interfaces.insert(s.name.to_string());
+            }
+            opts.defined_types.insert(s.name.to_string());
+        }
+        let names = self
+            .enums
+            .iter()
+            .map(|e| &e.name)
+            .chain(self.aliases.iter().map(|a| &a.name))
+            .chain(self.overrides.iter().map(|(name, _)| name));
+        for name in names {
+            opts.defined_types.insert(name.to_string());
+        }
+        // Only types from outside the input need --bitflags
+        for b in self.bitflags.iter() {
+            if !opts.bitflags.contains(&b.name) {
+                opts.bitflags.push(b.name.to_string());
+            }
+        }
+        for m in self.modules.iter() {
+            opts.modules.insert(m.name.to_string());
+            m.register_interfaces(opts);
+        }
+    }
+
+    fn to_ts(&self, opts: &Options) -> String {
+        format!("// {}\n", self.name) + &self.items_to_ts(opts)
+    }
+
+    fn items_to_ts(&self, opts: &Options) -> String {
+        let mut output = String::new();
+        if opts.bitflags_consts {
+            for b in self.bitflags.iter() {
+                output += &b.to_ts();
+            }
+        }
+        for c in self.consts.iter() {
+            output += &c.to_ts();
+        }
+        for (name, ty) in self.overrides.iter() {
+            output += &format!("export type {} = {};\n", name, ty);
+        }
+        // Generic types are replaced by their instantiations when
+        // monomorphizing
+        for a in self.aliases.iter() {
+            if !opts.monomorphize || a.generics.is_empty() {
+                output += &a.to_ts(opts);
+            }
+        }
+        for e in self.enums.iter() {
+            if !opts.monomorphize || e.generics.is_empty() {
+                output += &e.to_ts(opts);
+            }
+        }
+        for s in self.structs.iter() {
+            if !opts.monomorphize || s.generics.is_empty() {
+                output += &s.to_ts(opts);
+            }
+        }
+        for m in self.modules.iter() {
+            let items = m.items_to_ts(opts);
+            if opts.module_namespaces {
+                output += &namespace(&m.name, &items);
+            } else {
+                output += &items;
+            }
+        }
+        output
+    }
+
+    /// Add a concrete copy of the generic type that `ty` refers to,
+    /// if it's defined in this file or its modules
+    fn instantiate(&mut self, ty: &SimpleType, name: &str, queue: &mut Vec<SimpleType>) -> bool {
+        let generic_name = ty.path.last().unwrap();
+        let generic_struct = self
+            .structs
+            .iter()
+            .find(|s| &s.name == generic_name && !s.generics.is_empty());
+        if let Some(s) = generic_struct {
+            let args = type_args(&s.generics, ty);
+            let mut concrete = s.clone();
+            concrete.name = name.to_string();
+            concrete.generics.clear();
+            for field in concrete.fields.iter_mut() {
+                field.ty = field.ty.substitute(&args);
+                field.ty.named_uses(queue);
+            }
+            self.structs.push(concrete);
+            return true;
+        }
+        let generic_enum = self
+            .enums
+            .iter()
+            .find(|e| &e.name == generic_name && !e.generics.is_empty());
+        if let Some(e) = generic_enum {
+            let args = type_args(&e.generics, ty);
+            let mut concrete = e.clone();
+            concrete.name = name.to_string();
+            concrete.generics.clear();
+            for v in concrete.variants.iter_mut() {
+                for f in v.fields.iter_mut() {
+                    f.ty = f.ty.substitute(&args);
+                    f.ty.named_uses(queue);
+                }
+            }
+            self.enums.push(concrete);
+            return true;
+        }
+        let generic_alias = self
+            .aliases
+            .iter()
+            .find(|a| &a.name == generic_name && !a.generics.is_empty());
+        if let Some(a) = generic_alias {
+            let args = type_args(&a.generics, ty);
+            let mut concrete = a.clone();
+            concrete.name = name.to_string();
+            concrete.generics.clear();
+            concrete.ty = concrete.ty.substitute(&args);
+            concrete.ty.named_uses(queue);
+            self.aliases.push(concrete);
+            return true;
+        }
+        self.modules
+            .iter_mut()
+            .any(|m| m.instantiate(ty, name, queue))
+    }
+}
+
+/// Find type aliases that refer back to themselves without an object
+/// or array in between, which TypeScript rejects. Interfaces can
+/// always be recursive.
+fn alias_cycles(files: &[SimpleFile], opts: &Options) -> HashSet<String> {
+    let mut aliases: HashMap<&str, Vec<&str>> = HashMap::new();
+    for f in files.iter().flat_map(|f| f.all_files()) {
+        for s in f.structs.iter() {
+            if let Some(field) = s.newtype_field() {
+                if field.type_override.is_none() && field.with.is_none() {
+                    aliases.insert(&s.name, field.ty.direct_refs(opts));
+                }
+            }
+        }
+        for a in f.aliases.iter() {
+            aliases.insert(&a.name, a.ty.direct_refs(opts));
+        }
+        // Untagged newtype variants are bare members of the union
+        for e in f
+            .enums
+            .iter()
+            .filter(|e| e.tagging == EnumTagging::Untagged)
+        {
+            let refs = e
+                .variants
+                .iter()
+                .filter(|v| v.fields.len() == 1)
+                .flat_map(|v| v.fields[0].ty.direct_refs(opts))
+                .collect();
+            aliases.insert(&e.name, refs);
+        }
+    }
+
+    let mut cycles = HashSet::new();
+    for (start, refs) in aliases.iter() {
+        let mut stack = refs.clone();
+        let mut seen = HashSet::new();
+        while let Some(name) = stack.pop() {
+            if name == *start {
+                cycles.insert(start.to_string());
+                break;
+            }
+            if seen.insert(name) {
+                if let Some(refs) = aliases.get(name) {
+                    stack.extend(refs);
+                }
+            }
+        }
+    }
+    cycles
+}
+
+/// Map a generic type's parameters to the arguments of an
+/// instantiation, using defaults for missing arguments
+fn type_args(params: &[TypeParam], ty: &SimpleType) -> HashMap<String, SimpleType> {
+    let mut args = HashMap::new();
+    for (i, param) in params.iter().enumerate() {
+        if let Some(arg) = ty.generic_args.get(i).or(param.default.as_ref()) {
+            args.insert(param.name.to_string(), arg.clone());
+        }
+    }
+    args
+}
+
+const MAX_MONO_DEPTH: usize = 8;
+
+/// Add a concrete copy of a generic type for each instantiation used
+/// by the other types, for --monomorphize
+fn monomorphize(files: &mut [SimpleFile]) {
+    let mut known = HashSet::new();
+    let mut queue = Vec::new();
+    // Generic types that can be used without arguments, like
+    // `Response` for `Response<T = ()>`
+    let mut defaulted = HashSet::new();
+    for f in files.iter().flat_map(|f| f.all_files()) {
+        let generics = f
+            .structs
+            .iter()
+            .map(|s| (&s.name, &s.generics))
+            .chain(f.enums.iter().map(|e| (&e.name, &e.generics)))
+            .chain(f.aliases.iter().map(|a| (&a.name, &a.generics)));
+        for (name, params) in generics {
+            if !params.is_empty() && params.iter().all(|p| p.default.is_some()) {
+                defaulted.insert(name.to_string());
+            }
+        }
+        for s in f.structs.iter().filter(|s| s.generics.is_empty()) {
+            known.insert(s.name.to_string());
+            for field in s.fields.iter() {
+                field.ty.named_uses(&mut queue);
+            }
+        }
+        for e in f.enums.iter().filter(|e| e.generics.is_empty()) {
+            known.insert(e.name.to_string());
+            for v in e.variants.iter() {
+                for f in v.fields.iter() {
+                    f.ty.named_uses(&mut queue);
+                }
+            }
+        }
+        for a in f.aliases.iter().filter(|a| a.generics.is_empty()) {
+            known.insert(a.name.to_string());
+            a.ty.named_uses(&mut queue);
+        }
+    }
+
+    while let Some(ty) = queue.pop() {
+        // A bare reference is only an instantiation if all the
+        // parameters have defaults, and keeps the generic type's name
+        let bare = ty.generic_args.is_empty();
+        if bare && !ty.path.last().is_some_and(|name| defaulted.contains(name)) {
+            continue;
+        }
+        let name = ty.mono_name();
+        if known.contains(&name) {
+            continue;
+        }
+        // A recursive type like `Nested<T> { inner: Nested<Vec<T>> }`
+        // has infinitely many instantiations
+        if ty.depth() > MAX_MONO_DEPTH {
+            eprintln!(
+                "{}: generic arguments nested too deeply to monomorphize",
+                name
+            );
+            known.insert(name);
+            continue;
+        }
+        if files
+            .iter_mut()
+            .any(|f| f.instantiate(&ty, &name, &mut queue))
+        {
+            known.insert(name);
+        }
+    }
+}
+
+/// Get the type of fields using serde_bytes, which works on both
+/// byte buffers and optional byte buffers
+fn serde_bytes_type(with: &str, ty: &SimpleType, opts: &Options) -> Option<String> {
+    if with != "serde_bytes" {
+        None
+    } else if ty.is_option() {
+        Some(format!("{} | null", opts.bytes))
+    } else {
+        Some(opts.bytes.to_string())
+    }
+}
+
+/// Get the type of fields using one of chrono's or time's serde
+/// modules
+fn known_with_type(with: &str) -> Option<String> {
+    if with.starts_with("time::serde::") {
+        let (module, nullable) = match with.strip_suffix("::option") {
+            Some(module) => (module, " | null"),
+            None => (with, ""),
+        };
+        let ty = match module.rsplit("::").next().unwrap() {
+            "rfc3339" | "rfc2822" | "iso8601" => "string",
+            "timestamp" => "number",
+            _ => return None,
+        };
+        return Some(format!("{}{}", ty, nullable));
+    }
+
+    // humantime_serde writes durations like "1h 30m" and times as
+    // RFC 3339 strings
+    match with {
+        "humantime_serde" => return Some("string".to_string()),
+        "humantime_serde::option" => return Some("string | null".to_string()),
+        _ => {}
+    }
+
+    let module = with.rsplit("::").next().unwrap();
+    let is_chrono = with == module || with.starts_with("chrono::");
+    let ty = match module {
+        "ts_seconds" | "ts_milliseconds" | "ts_microseconds" | "ts_nanoseconds" => "number",
+        "ts_seconds_option"
+        | "ts_milliseconds_option"
+        | "ts_microseconds_option"
+        | "ts_nanoseconds_option" => "number | null",
+        _ => return None,
+    };
+    if is_chrono {
+        Some(ty.to_string())
+    } else {
+        None
+    }
+}
+
+/// Split a `KEY=VALUE` command line argument
+fn split_mapping(arg: &str) -> Option<(String, String)> {
+    let mut parts = arg.splitn(2, '=');
+    let key = parts.next()?.trim();
+    let value = parts.next()?.trim();
+    if key.is_empty() || value.is_empty() {
+        return None;
+    }
+    Some((key.to_string(), value.to_string()))
+}
+
+fn is_mapping(arg: String) -> Result<(), String> {
+    match split_mapping(&arg) {
+        Some(_) => Ok(()),
+        None => Err(format!("expected KEY=VALUE, got \"{}\"", arg)),
+    }
+}
+
+/// Write a file by renaming a temporary file next to it, so that
+/// readers never see partial output
+fn write_atomic(path: &std::path::Path, contents: &str) -> std::io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    fs::write(&tmp, contents)?;
+    fs::rename(&tmp, path).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })
+}
+
+/// Write an output file, exiting on failure
+fn write_or_exit(path: &std::path::Path, contents: &str) {
+    if let Err(err) = write_atomic(path, contents) {
+        eprintln!("{}: {}", path.display(), err);
+        std::process::exit(1);
+    }
+}
+
+/// Name of the output module holding the preamble in --out-dir mode
+const PREAMBLE_MODULE: &str = "preamble";
+
+/// Name of the output module re-exporting all the others in
+/// --out-dir mode
+const INDEX_MODULE: &str = "index";
+
+/// Get the names of the top-level types and values exported by
+/// generated TypeScript, along with the keyword declaring them
+fn exported_names(ts: &str) -> Vec<(&str, &str)> {
+    let mut names = Vec::new();
+    for line in ts.lines() {
+        let mut words = line.split(' ');
+        if words.next() != Some("export") {
+            continue;
+        }
+        if let (Some(kw @ ("type" | "interface" | "const" | "namespace")), Some(name)) =
+            (words.next(), words.next())
+        {
+            let end = name.find(|c| !is_ident_char(c)).unwrap_or(name.len());
+            names.push((kw, &name[..end]));
+        }
+    }
+    names
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+/// Get the identifiers used in generated TypeScript, skipping string
+/// literals and comments
+fn ts_identifiers(ts: &str) -> HashSet<&str> {
+    let mut names = HashSet::new();
+    let mut rest = ts;
+    while let Some(c) = rest.chars().next() {
+        let end = if rest.starts_with("//") {
+            rest.find('\n').unwrap_or(rest.len())
+        } else if rest.starts_with("/*") {
+            rest.find("*/").map_or(rest.len(), |i| i + 2)
+        } else if c == '"' || c == '\'' {
+            let mut escaped = false;
+            let close = rest.char_indices().skip(1).find(|&(_, ch)| {
+                let found = !escaped && ch == c;
+                escaped = !escaped && ch == '\\';
+                found
+            });
+            close.map_or(rest.len(), |(i, _)| i + 1)
+        } else if is_ident_char(c) {
+            let end = rest.find(|c| !is_ident_char(c)).unwrap_or(rest.len());
+            // Skip numbers
+            if !c.is_ascii_digit() {
+                names.insert(&rest[..end]);
+            }
+            end
+        } else {
+            c.len_utf8()
+        };
+        rest = &rest[end..];
+    }
+    names
+}
+
+/// Get the `import type` statements for the names a module refers
+/// to that are defined in other modules. Names the module defines
+/// itself are never imported.
+fn imports(
+    module: &str,
+    defined: &[String],
+    refs: &HashSet<String>,
+    symbols: &HashMap<&str, &str>,
+) -> String {
+    let mut by_module: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for name in refs.iter().filter(|name| !defined.contains(name)) {
+        if let Some(&m) = symbols.get(name.as_str()) {
+            by_module.entry(m).or_default().push(name);
+        }
+    }
+    let mut out = String::new();
+    for (m, mut names) in by_module {
+        names.sort_unstable();
+        out += &format!(
+            "import type {{ {} }} from \"{}\";\n",
+            names.join(", "),
+            relative_module(module, m)
+        );
+    }
+    out
+}
+
+/// Get the import specifier of module `to` from module `from`, where
+/// modules are paths like `api/users` relative to the output
+/// directory
+fn relative_module(from: &str, to: &str) -> String {
+    let from_dirs: Vec<_> = from.split('/').collect();
+    let from_dirs = &from_dirs[..from_dirs.len() - 1];
+    let to_parts: Vec<_> = to.split('/').collect();
+    let common = from_dirs
+        .iter()
+        .zip(to_parts.iter())
+        .take_while(|(a, b)| a == b)
+        .count()
+        .min(to_parts.len() - 1);
+    let up = from_dirs.len() - common;
+    let prefix = if up == 0 {
+        "./".to_string()
+    } else {
+        "../".repeat(up)
+    };
+    prefix + &to_parts[common..].join("/")
+}
+
+/// Get the output module of an input file. With a source root, the
+/// module mirrors the file's place in the Rust module tree, e.g.
+/// `api/users` for `src/api/users.rs` or `src/api/users/mod.rs`.
+fn module_path(input: &std::path::Path, root: Option<&std::path::Path>) -> Result<String, String> {
+    let relative = match root {
+        Some(root) => input.strip_prefix(root).map_err(|_| {
+            format!(
+                "{}: not inside the source root {}",
+                input.display(),
+                root.display()
+            )
+        })?,
+        None => std::path::Path::new(input.file_name().unwrap_or_default()),
+    };
+    let mut parts: Vec<String> = relative
+        .with_extension("")
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    if parts.len() > 1 && parts.last().is_some_and(|p| p == "mod") {
+        parts.pop();
+    }
+    Ok(parts.join("/"))
+}
+
+/// Generate a TypeScript module for each input file, plus one for the
+/// preamble, importing the types each one uses from the others. The
+/// module paths of the files come from `module_path`. Returns pairs
+/// of file paths and contents.
+fn split_output(
+    files: &[SimpleFile],
+    module_paths: &[String],
+    opts: &Options,
+) -> Result<Vec<(String, String)>, String> {
+    let mut modules: Vec<(String, String)> = Vec::new();
+    // The names each module defines and refers to
+    let mut names = Vec::new();
+    for (f, module) in files.iter().zip(module_paths) {
+        if module == INDEX_MODULE || module == PREAMBLE_MODULE {
+            return Err(format!("{}: {}.ts is reserved", f.name, module));
+        }
+        if modules.iter().any(|(m, _)| m == module) {
+            return Err(format!(
+                "{}: more than one input maps to {}.ts",
+                f.name, module
+            ));
+        }
+        modules.push((module.to_string(), f.to_ts(opts)));
+        names.push((f.defined_names(opts), f.referenced_names(opts)));
+    }
+    let all: String = modules.iter().map(|(_, ts)| ts.as_str()).collect();
+    let preamble = preamble(opts, &all);
+    if !preamble.is_empty() {
+        let defined = exported_names(&preamble)
+            .into_iter()
+            .map(|(_, name)| name.to_string())
+            .collect();
+        modules.insert(0, (PREAMBLE_MODULE.to_string(), preamble));
+        names.insert(0, (defined, HashSet::new()));
+    }
+
+    // The first definition of a name wins
+    let mut symbols = HashMap::new();
+    for ((module, _), (defined, _)) in modules.iter().zip(names.iter()) {
+        for name in defined.iter() {
+            symbols.entry(name.as_str()).or_insert(module.as_str());
+        }
+    }
+    let mut outputs: Vec<_> = modules
+        .iter()
+        .zip(names.iter())
+        .map(|((module, ts), (defined, refs))| {
+            let out = imports(module, defined, refs, &symbols) + ts;
+            (format!("{}.ts", module), out)
+        })
+        .collect();
+
+    // Also re-export values like enum consts
+    let mut exports = HashMap::new();
+    for (module, ts) in modules.iter() {
+        for (_, name) in exported_names(ts) {
+            exports.entry(name).or_insert(module.as_str());
+        }
+    }
+    outputs.push((format!("{}.ts", INDEX_MODULE), barrel(&modules, &exports)));
+    Ok(outputs)
+}
+
+/// Get an index module re-exporting everything in the other modules,
+/// so imports don't depend on the file layout. Names are listed
+/// explicitly because `export *` drops names defined in two modules.
+fn barrel(modules: &[(String, String)], symbols: &HashMap<&str, &str>) -> String {
+    let mut out = String::new();
+    for (module, ts) in modules.iter() {
+        let mut types = Vec::new();
+        let mut values = Vec::new();
+        for (kw, name) in exported_names(ts) {
+            if symbols.get(name) != Some(&module.as_str()) {
+                continue;
+            }
+            // A const re-export also carries a type of the same name
+            if kw == "const" {
+                types.retain(|n| *n != name);
+                values.push(name);
+            } else if !types.contains(&name) && !values.contains(&name) {
+                types.push(name);
+            }
+        }
+        let from = relative_module(INDEX_MODULE, module);
+        if !types.is_empty() {
+            out += &format!(
+                "export type {{ {} }} from \"{}\";\n",
+                types.join(", "),
+                from
+            );
+        }
+        if !values.is_empty() {
+            out += &format!("export {{ {} }} from \"{}\";\n", values.join(", "), from);
+        }
+    }
+    out
+}
+
+/// Convert generated TypeScript to ambient declarations for a .d.ts
+/// file. Top-level types lose their `export`, so the file declares
+/// global types instead of being a module, and consts become
+/// declarations of their literal types.
+fn to_declarations(ts: &str) -> String {
+    let mut out = String::new();
+    let mut in_object = false;
+    for line in ts.lines() {
+        let trimmed = line.trim_start();
+        let pad = &line[..line.len() - trimmed.len()];
+        // Namespace members stay exported
+        let (export, declare) = if pad.is_empty() {
+            ("", "declare ")
+        } else {
+            ("export ", "export ")
+        };
+        let line = if in_object {
+            if trimmed == "} as const;" {
+                in_object = false;
+                format!("{}}};", pad)
+            } else {
+                match trimmed.strip_suffix(',') {
+                    Some(entry) => format!("{}readonly {};", pad, entry),
+                    None => line.to_string(),
+                }
+            }
+        } else if let Some(rest) = trimmed.strip_prefix("export const ") {
+            match rest.strip_suffix(" = {") {
+                Some(name) => {
+                    in_object = true;
+                    format!("{}{}const {}: {{", pad, declare, name)
+                }
+                None => format!("{}{}const {}", pad, declare, rest.replacen(" = ", ": ", 1)),
+            }
+        } else if let Some(rest) = trimmed.strip_prefix("export namespace ") {
+            format!("{}{}namespace {}", pad, declare, rest)
+        } else if let Some(rest) = trimmed.strip_prefix("export ") {
+            format!("{}{}{}", pad, export, rest)
+        } else {
+            line.to_string()
+        };
+        out += &line;
+        out.push('\n');
+    }
+    out
+}
+
+/// Apply --namespace, --declare-module, and --emit dts to the
+/// generated types. The user's preamble and epilogue are added
+/// around the result as they are, since they can contain code that
+/// isn't valid in a declaration.
+fn wrap_output(
+    ts: &str,
+    snippets: (&str, &str),
+    namespace_name: Option<&str>,
+    module: Option<&str>,
+    dts: bool,
+) -> String {
+    let mut out = match namespace_name {
+        Some(name) => namespace(name, ts),
+        None => ts.to_string(),
+    };
+    if let Some(name) = module {
+        out = to_declarations(&declare_module(name, &out));
+    } else if dts {
+        out = to_declarations(&out);
+    }
+    let (preamble, epilogue) = snippets;
+    preamble.to_string() + &out + epilogue
+}
+
+const DEFAULT_HEADER: &str =
+    "// Generated by rsts v{version} from {inputs} (sha256: {sha256}) — do not edit";
+
+/// Fill in a header template from the names and contents of the
+/// input files. The hash covers all the inputs so tooling can tell
+/// when the output is stale.
+fn header(template: &str, inputs: &[(String, String)]) -> String {
+    let mut hasher = Sha256::new();
+    // The lengths keep the boundaries between inputs, so that `a`
+    // and `bc` don't hash the same as `abc`
+    for (name, contents) in inputs.iter() {
+        hasher.update((name.len() as u64).to_le_bytes());
+        hasher.update(name.as_bytes());
+        hasher.update((contents.len() as u64).to_le_bytes());
+        hasher.update(contents.as_bytes());
+    }
+    let sha256: String = hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    let names: Vec<_> = inputs.iter().map(|(name, _)| name.as_str()).collect();
+    let mut out = template
+        .replace("{version}", env!("CARGO_PKG_VERSION"))
+        .replace("{inputs}", &names.join(", "))
+        .replace("{sha256}", &sha256);
+    if !out.ends_with('\n') {
+        out.push('\n');
+    }
+    out
+}
+
+/// Read a user-provided TypeScript snippet, exiting on failure
+fn read_snippet(path: Option<&str>) -> String {
+    let path = match path {
+        Some(path) => path,
+        None => return String::new(),
+    };
+    match fs::read_to_string(path) {
+        Ok(mut snippet) => {
+            if !snippet.is_empty() && !snippet.ends_with('\n') {
+                snippet.push('\n');
+            }
+            snippet
+        }
+        Err(err) => {
+            eprintln!("{}: {}", path, err);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Get the type aliases used by the generated types in `ts`
+fn preamble(opts: &Options, ts: &str) -> String {
+    let mut out = String::new();
+    let (name, ty) = &opts.datetime_alias;
+    if ts_identifiers(ts).contains(name.as_str()) {
+        out += &format!("export type {} = {};\n", name, ty);
+    }
+    if opts.uuid_alias {
+        out += "export type Uuid = string & { readonly __brand: \"Uuid\" };\n";
+    }
+    if opts.url_alias {
+        out += "export type Url = string;\n";
+    }
+    if opts.date_aliases {
+        for (_, alias) in NAIVE_DATE_TYPES.iter() {
+            out += &format!("export type {} = string;\n", alias);
+        }
+    }
+    out
+}
+
+fn is_usize(arg: String) -> Result<(), String> {
+    arg.parse::<usize>()
+        .map(|_| ())
+        .map_err(|_| format!("expected a number, got \"{}\"", arg))
+}
+
+fn main() {
+    let matches = clap_app!(rsts =>
+        (about: "Convert Rust types to Typescript")
+        (@arg INPUT: +required +multiple "typescript file(s)")
+        (@arg OUT: -o --out +takes_value
+         "Write the output to this file instead of stdout")
+        (@arg OUT_DIR: --("out-dir") +takes_value conflicts_with[OUT]
+         "Write a .ts file for each input file to this directory, \
+          plus preamble.ts, importing types across files, and an \
+          index.ts re-exporting all of them")
+        (@arg EMIT: --emit +takes_value possible_value[module dts] conflicts_with[OUT_DIR]
+         "Emit an ES module (default) or ambient declarations for a \
+          .d.ts file")
+        (@arg NAMESPACE: --namespace +takes_value conflicts_with[OUT_DIR]
+         "Wrap the output in a namespace with this name")
+        (@arg DECLARE_MODULE: --("declare-module") +takes_value conflicts_with[OUT_DIR EMIT]
+         "Emit the output as an ambient module declaration with this \
+          name, e.g. my-api-types")
+        (@arg HEADER: --header +takes_value
+         "Header at the top of the output, where {version}, {inputs}, \
+          and {sha256} are replaced by the rsts version, the input \
+          file names, and a hash of the inputs")
+        (@arg NO_HEADER: --("no-header") conflicts_with[HEADER]
+         "Don't put a generated-file header at the top of the output")
+        (@arg PREAMBLE: --preamble +takes_value
+         "TypeScript file to insert before the generated types, in \
+          every output file. It's inserted as it is, outside \
+          --namespace and --declare-module.")
+        (@arg EPILOGUE: --epilogue +takes_value
+         "TypeScript file to insert after the generated types, in \
+          every output file. It's inserted as it is, outside \
+          --namespace and --declare-module.")
+        (@arg SRC_ROOT: --("src-root") +takes_value requires[OUT_DIR]
+         "With --out-dir, mirror the input files' paths under this \
+          directory, e.g. src for src/api/users.rs -> api/users.ts")
+        (@arg DIRECTION: --direction +takes_value
+         possible_value[serialize deserialize both]
+         "Generate types for JSON produced by Rust (serialize), \
+          consumed by Rust (deserialize), or both (default)")
+        (@arg COLLAPSE_OPTION: --("collapse-option")
+         "Emit `field?: T` instead of `field?: T | null` for Option \
+          fields with skip_serializing_if")
+        (@arg OTHER_FALLBACK: --("other-fallback") +takes_value
+         "Type of unknown variant names for enums with a \
+          #[serde(other)] variant [default: (string & {})]")
+        (@arg WITH_TYPE: --("with-type") +takes_value +multiple number_of_values(1)
+         {is_mapping}
+         "Type of fields with #[serde(with = \"...\")], e.g. \
+          chrono::serde::ts_seconds=number")
+        (@arg DERIVE_ALIAS: --("derive-alias") +takes_value +multiple number_of_values(1)
+         "Derive that also marks a struct as serializable, e.g. \
+          my_serde::Serialize")
+        (@arg INT64: --int64 +takes_value possible_value[number string bigint]
+         "Type of i64 and u64 [default: number]")
+        (@arg DECIMAL: --decimal +takes_value possible_value[string number]
+         "Type of Decimal and BigDecimal, depending on the serde \
+          features they're built with [default: string]")
+        (@arg INT128: --int128 +takes_value possible_value[number string bigint]
+         "Type of i128 and u128 [default: number]")
+        (@arg MAX_TUPLE_LEN: --("max-tuple-len") +takes_value {is_usize}
+         "Longest fixed-size array emitted as a tuple rather than \
+          an array [default: 4]")
+        (@arg JSON_VALUE: --("json-value-type") +takes_value
+         "Type of serde_json::Value, e.g. a JsonValue alias from \
+          the preamble [default: unknown]")
+        (@arg UUID_ALIAS: --("uuid-alias")
+         "Emit Uuid fields as a branded Uuid type instead of string")
+        (@arg URL_ALIAS: --("url-alias")
+         "Emit Url fields as a Url type alias instead of string")
+        (@arg DATE_ALIASES: --("date-aliases")
+         "Emit NaiveDate, NaiveDateTime, and NaiveTime fields as \
+          IsoDate, IsoDateTime, and IsoTime aliases instead of string")
+        (@arg DATETIME_TYPE: --("datetime-type") +takes_value +multiple number_of_values(1)
+         {is_mapping}
+         "Type of DateTime with the given offset, e.g. \
+          FixedOffset=string [default: the --datetime-alias alias]")
+        (@arg DATETIME_ALIAS: --("datetime-alias") +takes_value {is_mapping}
+         "Name and type of the alias for DateTime<Utc>, e.g. \
+          Instant=Date [default: DateTimeUtc=string]")
+        (@arg TIME_TYPE: --("time-type") +takes_value +multiple number_of_values(1)
+         {is_mapping}
+         "Type of a time crate type, e.g. OffsetDateTime=number \
+          [default: string]")
+        (@arg CHRONO_DURATION: --("chrono-duration") +takes_value possible_value[string number]
+         "Type of chrono::Duration, as ISO 8601 strings or \
+          milliseconds [default: string]")
+        (@arg BYTES_BASE64: --("bytes-base64")
+         "Emit Bytes and serde_bytes fields as base64 strings \
+          instead of number[]")
+        (@arg BITFLAGS: --bitflags +takes_value +multiple number_of_values(1)
+         "Name of a type generated by bitflags! outside the input \
+          files")
+        (@arg BITFLAGS_TYPE: --("bitflags-type") +takes_value possible_value[number string]
+         "Type of bitflags types [default: number]")
+        (@arg BITFLAGS_CONSTS: --("bitflags-consts")
+         "Emit a const object with the flag values of each \
+          bitflags! type")
+        (@arg BSON_PLAIN: --("bson-plain")
+         "Emit bson ObjectId and DateTime as strings instead of \
+          extended JSON objects, for models using bson's serde helpers")
+        (@arg STRING_TYPE: --("string-type") +takes_value +multiple number_of_values(1)
+         "Name of a type that serializes as a string")
+        (@arg WRAPPER_TYPE: --("wrapper-type") +takes_value +multiple number_of_values(1)
+         "Name of a generic container that serializes the same as \
+          its type argument, e.g. Tracked or my_crate::Tracked for \
+          Tracked<T>")
+        (@arg TRAIT_TYPE: --("trait-type") +takes_value +multiple number_of_values(1)
+         {is_mapping}
+         "Type of trait objects for a trait, e.g. Error=string \
+          [default: unknown]")
+        (@arg UNIT_TYPE: --("unit-type") +takes_value
+         "Type of `()` and unit structs like `struct Heartbeat;` \
+          [default: null]")
+        (@arg MONOMORPHIZE: --monomorphize
+         "Emit a named type like PaginatedUser for each use of a \
+          generic type instead of TypeScript generics")
+        (@arg INCLUDE_CONSTS: --("include-consts")
+         "Emit consts with integer, float, string, or bool values")
+        (@arg INCLUDE_STATICS: --("include-statics")
+         "Emit immutable statics with integer, float, string, or \
+          bool values")
+        (@arg MODULE_NAMESPACES: --("module-namespaces")
+         "Emit the types in inline modules inside a namespace of the \
+          same name")
+        (@arg ENUM_CONSTS: --("enum-consts")
+         "Emit a const object with the discriminant values of enums \
+          like `enum Color { Red = 1 }`")
+        (@arg NON_EXHAUSTIVE_FALLBACK: --("non-exhaustive-fallback") +takes_value
+         "Extra member of #[non_exhaustive] enums for variants added \
+          later, e.g. '{ [k: string]: unknown }'")
+        (@arg INCLUDE_HIDDEN: --("include-hidden")
+         "Emit items marked #[doc(hidden)]")
+        (@arg EXCLUDE_ATTR: --("exclude-attr") +takes_value +multiple number_of_values(1)
+         "Skip items with this attribute, e.g. ts_skip for #[ts_skip]")
+        (@arg EXCLUDE: --exclude +takes_value +multiple number_of_values(1)
+         "Name of an item to skip")
+        (@arg ONLY_PUB: --("only-pub")
+         "Only emit pub items")
+        (@arg PUB_CRATE: --("pub-crate") requires[ONLY_PUB]
+         "With --only-pub, also emit pub(crate) items")
+    )
+    .get_matches();
+
+    let mut opts = Options::default();
+    if let Some(direction) = matches.value_of("DIRECTION") {
+        opts.direction = Direction::parse(direction).unwrap();
+    }
+    opts.collapse_option = matches.is_present("COLLAPSE_OPTION");
+    if let Some(fallback) = matches.value_of("OTHER_FALLBACK") {
+        opts.other_fallback = fallback.to_string();
+    }
+    if let Some(values) = matches.values_of("WITH_TYPE") {
+        for value in values {
+            let (module, ty) = split_mapping(value).unwrap();
+            opts.with_types.insert(module, ty);
+        }
+    }
+    if let Some(values) = matches.values_of("DERIVE_ALIAS") {
+        opts.derive_aliases = values.map(|v| v.to_string()).collect();
+    }
+    if let Some(int64) = matches.value_of("INT64") {
+        opts.int64 = BigIntType::parse(int64).unwrap();
+    }
+    if let Some(decimal) = matches.value_of("DECIMAL") {
+        opts.decimal = decimal.to_string();
+    }
+    if let Some(int128) = matches.value_of("INT128") {
+        opts.int128 = BigIntType::parse(int128).unwrap();
+    }
+    if let Some(len) = matches.value_of("MAX_TUPLE_LEN") {
+        opts.max_tuple_len = len.parse().unwrap();
+    }
+    if let Some(ty) = matches.value_of("JSON_VALUE") {
+        opts.json_value = ty.to_string();
+    }
+    opts.uuid_alias = matches.is_present("UUID_ALIAS");
+    opts.url_alias = matches.is_present("URL_ALIAS");
+    opts.date_aliases = matches.is_present("DATE_ALIASES");
+    if let Some(values) = matches.values_of("DATETIME_TYPE") {
+        for value in values {
+            let (offset, ty) = split_mapping(value).unwrap();
+            opts.datetime_types.insert(offset, ty);
+        }
+    }
+    if let Some(value) = matches.value_of("DATETIME_ALIAS") {
+        opts.datetime_alias = split_mapping(value).unwrap();
+    }
+    if let Some(values) = matches.values_of("TIME_TYPE") {
+        for value in values {
+            let (name, ty) = split_mapping(value).unwrap();
+            opts.time_types.insert(name, ty);
+        }
+    }
+    if let Some(ty) = matches.value_of("CHRONO_DURATION") {
+        opts.chrono_duration = ty.to_string();
+    }
+    if matches.is_present("BYTES_BASE64") {
+        opts.bytes = "string".to_string();
+    }
+    if let Some(values) = matches.values_of("BITFLAGS") {
+        opts.bitflags = values.map(|v| v.to_string()).collect();
+    }
+    if let Some(ty) = matches.value_of("BITFLAGS_TYPE") {
+        opts.bitflags_type = ty.to_string();
+    }
+    opts.bitflags_consts = matches.is_present("BITFLAGS_CONSTS");
+    opts.bson_plain = matches.is_present("BSON_PLAIN");
+    if let Some(values) = matches.values_of("STRING_TYPE") {
+        opts.string_types = values.map(|v| v.to_string()).collect();
+    }
+    if let Some(values) = matches.values_of("WRAPPER_TYPE") {
+        // Types are matched by their final path segment
+        opts.wrapper_types = values
+            .map(|v| v.rsplit("::").next().unwrap().to_string())
+            .collect();
+    }
+    if let Some(values) = matches.values_of("TRAIT_TYPE") {
+        for value in values {
+            let (name, ty) = split_mapping(value).unwrap();
+            opts.trait_types.insert(name, ty);
+        }
+    }
+    if let Some(ty) = matches.value_of("UNIT_TYPE") {
+        opts.unit_type = ty.to_string();
+    }
+    opts.monomorphize = matches.is_present("MONOMORPHIZE");
+    opts.include_consts = matches.is_present("INCLUDE_CONSTS");
+    opts.include_statics = matches.is_present("INCLUDE_STATICS");
+    opts.module_namespaces = matches.is_present("MODULE_NAMESPACES");
+    opts.enum_consts = matches.is_present("ENUM_CONSTS");
+    opts.non_exhaustive_fallback = matches
+        .value_of("NON_EXHAUSTIVE_FALLBACK")
+        .map(|ty| ty.to_string());
+    opts.include_hidden = matches.is_present("INCLUDE_HIDDEN");
+    if let Some(values) = matches.values_of("EXCLUDE_ATTR") {
+        opts.exclude_attrs = values.map(|v| v.to_string()).collect();
+    }
+    if let Some(values) = matches.values_of("EXCLUDE") {
+        opts.exclude = values.map(|v| v.to_string()).collect();
+    }
+    opts.only_pub = matches.is_present("ONLY_PUB");
+    opts.pub_crate = matches.is_present("PUB_CRATE");
+
+    let inputs: Vec<_> = matches
+        .values_of("INPUT")
+        .unwrap()
+        .map(|input| {
+            let path = std::path::Path::new(input);
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            (name, fs::read_to_string(path).expect("Unable to read file"))
+        })
+        .collect();
+    let mut files = Vec::new();
+    for (name, src) in inputs.iter() {
+        files.push(SimpleFile::parse(name.to_string(), src, &opts));
+    }
+    if opts.monomorphize {
+        monomorphize(&mut files);
+    }
+    for f in files.iter() {
+        f.register_interfaces(&mut opts);
+    }
+    opts.alias_cycles = alias_cycles(&files, &opts);
+    let mut cycles = opts.alias_cycles.iter().collect::<Vec<_>>();
+    cycles.sort();
+    for name in cycles {
+        eprintln!("{}: type alias refers to itself, emitting unknown", name);
+    }
+    let uses_int64 = files
+        .iter()
+        .flat_map(|f| f.all_files())
+        .flat_map(|f| f.types())
+        .any(|ty| ty.uses_int64());
+    if opts.int64 == BigIntType::Number && uses_int64 {
+        eprintln!(
+            "warning: i64 and u64 values above 2^53 will lose precision, \
+             see --int64"
+        );
+    }
+
+    let header = if matches.is_present("NO_HEADER") {
+        String::new()
+    } else {
+        header(
+            matches.value_of("HEADER").unwrap_or(DEFAULT_HEADER),
+            &inputs,
+        )
+    };
+
+    let user_preamble = read_snippet(matches.value_of("PREAMBLE"));
+    let epilogue = read_snippet(matches.value_of("EPILOGUE"));
+
+    if let Some(dir) = matches.value_of("OUT_DIR") {
+        let dir = std::path::Path::new(dir);
+        let root = matches.value_of("SRC_ROOT").map(std::path::Path::new);
+        let outputs = matches
+            .values_of("INPUT")
+            .unwrap()
+            .map(|input| module_path(std::path::Path::new(input), root))
+            .collect::<Result<Vec<_>, _>>()
+            .and_then(|module_paths| split_output(&files, &module_paths, &opts))
+            .unwrap_or_else(|err| {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            });
+        for (name, contents) in outputs {
+            let path = dir.join(name);
+            let parent = path.parent().unwrap();
+            if let Err(err) = fs::create_dir_all(parent) {
+                eprintln!("{}: {}", parent.display(), err);
+                std::process::exit(1);
+            }
+            let contents = header.clone() + &user_preamble + &contents + &epilogue;
+            write_or_exit(&path, &contents);
+        }
+        return;
+    }
+
+    let mut body = String::new();
+    for f in files {
+        body += &f.to_ts(&opts);
+    }
+    let aliases = preamble(&opts, &(user_preamble.clone() + &body + &epilogue));
+    let out = header
+        + &wrap_output(
+            &(aliases + &body),
+            (&user_preamble, &epilogue),
+            matches.value_of("NAMESPACE"),
+            matches.value_of("DECLARE_MODULE"),
+            matches.value_of("EMIT") == Some("dts"),
+        );
+    match matches.value_of("OUT") {
+        Some(path) => write_or_exit(std::path::Path::new(path), &out),
+        None => print!("{}", out),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simple_type_number() {
+        let st = SimpleType::new(vec!["i32".to_string()], vec![]);
+        assert_eq!(st.to_ts(&Options::default()), "number");
+    }
+
+    #[test]
+    fn simple_type_string() {
+        let st = SimpleType::new(vec!["String".to_string()], vec![]);
+        assert_eq!(st.to_ts(&Options::default()), "string");
+    }
+
+    #[test]
+    fn simple_type_bool_char() {
+        let st = SimpleType::new(vec!["bool".to_string()], vec![]);
+        assert_eq!(st.to_ts(&Options::default()), "boolean");
+        let st = SimpleType::new(vec!["char".to_string()], vec![]);
+        assert_eq!(st.to_ts(&Options::default()), "string");
+    }
+
+    #[test]
+    fn simple_type_sizes() {
+        let st = SimpleType::new(vec!["usize".to_string()], vec![]);
+        assert_eq!(st.to_ts(&Options::default()), "number");
+        let st = SimpleType::new(vec!["u128".to_string()], vec![]);
+        assert_eq!(st.to_ts(&Options::default()), "number");
+        let opts = Options {
+            int128: BigIntType::BigInt,
+            ..Default::default()
+        };
+        assert_eq!(st.to_ts(&opts), "bigint");
+    }
+
+    #[test]
+    fn simple_type_option() {
+        let st = SimpleType::new(
+            vec!["Option".to_string()],
+            vec![SimpleType::new(vec!["i32".to_string()], vec![])],
+        );
+
+        assert_eq!(st.to_ts(&Options::default()), "number | null");
+    }
+
+    #[test]
+    fn simple_type_vec() {
+        let st = SimpleType::new(
+            vec!["Vec".to_string()],
+            vec![SimpleType::new(vec!["i32".to_string()], vec![])],
+        );
+
+        assert_eq!(st.to_ts(&Options::default()), "number[]");
+    }
+
+    #[test]
+    fn simple_type_vec_option() {
+        let st = SimpleType::new(
+            vec!["Vec".to_string()],
+            vec![SimpleType::new(
+                vec!["Option".to_string()],
+                vec![SimpleType::new(vec!["i32".to_string()], vec![])],
+            )],
+        );
+
+        assert_eq!(st.to_ts(&Options::default()), "(number | null)[]");
+    }
+
+    #[test]
+    fn newtype() {
+        let s = SimpleStruct {
+            name: "MyType".to_string(),
+            fields: vec![SimpleField::new(
+                None,
+                SimpleType::new(vec!["String".to_string()], vec![]),
+            )],
+            rename_all: None,
+            transparent: false,
+            deny_unknown_fields: false,
+            generics: vec![],
+            unit: false,
+            non_exhaustive: false,
+            doc: Vec::new(),
+        };
+
+        assert_eq!(
+            s.to_ts(&Options::default()),
+            "export type MyType = string;\n"
+        )
+    }
+
+    #[test]
+    fn rename_all_fields() {
+        let rule = RenameRule::Camel;
+        assert_eq!(rule.apply_to_field("user_id"), "userId");
+        let rule = RenameRule::ScreamingKebab;
+        assert_eq!(rule.apply_to_field("user_id"), "USER-ID");
+
+        let s: syn::ItemStruct = syn::parse_str(
+            "#[derive(Serialize)] #[serde(rename_all = \"camelCase\")]
+             struct X { first_name: String }",
+        )
+        .unwrap();
+        let s = SimpleStruct::new(&s).unwrap();
+        assert_eq!(
+            s.to_ts(&Options::default()),
+            "export interface X {\n  firstName: string;\n}\n"
+        );
+    }
+
+    #[test]
+    fn rename_field() {
+        let s: syn::ItemStruct = syn::parse_str(
+            "#[derive(Serialize)] #[serde(rename_all = \"camelCase\")]
+             struct X { #[serde(rename = \"ID\")] user_id: i32, user_name: String }",
+        )
+        .unwrap();
+        let s = SimpleStruct::new(&s).unwrap();
+        assert_eq!(
+            s.to_ts(&Options::default()),
+            "export interface X {\n  ID: number;\n  userName: string;\n}\n"
+        );
+    }
+
+    #[test]
+    fn datetime() {
+        let t = SimpleType::new(
+            vec!["DateTime".to_string()],
+            vec![SimpleType::new(vec!["Utc".to_string()], vec![])],
+        );
+        assert_eq!(t.to_ts(&Options::default()), "DateTimeUtc");
+    }
+
+    #[test]
+    fn hashmap() {
+        let t = SimpleType::new(
+            vec!["HashMap".to_string()],
+            vec![
+                SimpleType::new(vec!["String".to_string()], vec![]),
+                SimpleType::new(vec!["i32".to_string()], vec![]),
+            ],
+        );
+        assert_eq!(t.to_ts(&Options::default()), "Record<string, number>");
+    }
+
+    #[test]
+    fn enum_to_ts() {
+        let e = SimpleEnum {
+            name: "myEnum".to_string(),
+            variants: vec![SimpleVariant::new("myVariant".to_string(), vec![])],
+            rename_all: None,
+            tagging: EnumTagging::External,
+            repr: false,
+            rename_all_fields: None,
+            generics: Vec::new(),
+            non_exhaustive: false,
+            doc: Vec::new(),
+        };
+        assert_eq!(
+            e.to_ts(&Options::default()),
+            "export type myEnum =\n  \"myVariant\";\n"
+        );
+    }
+
+    #[test]
+    fn rename_variants() {
+        let rule = RenameRule::ScreamingSnake;
+        assert_eq!(rule.apply_to_variant("NotFound"), "NOT_FOUND");
+        let rule = RenameRule::Kebab;
+        assert_eq!(rule.apply_to_variant("NotFound"), "not-found");
+
+        let e: syn::ItemEnum = syn::parse_str(
+            "#[serde(rename_all = \"snake_case\")]
+             enum E { NotFound, #[serde(rename = \"gone\")] Deleted }",
+        )
+        .unwrap();
+        let e = SimpleEnum::from_syn_type(&e).unwrap();
+        assert_eq!(
+            e.to_ts(&Options::default()),
+            "export type E =\n  \"not_found\" |\n  \"gone\";\n"
+        );
+    }
+
+    #[test]
+    fn internally_tagged_enum() {
+        let e: syn::ItemEnum =
+            syn::parse_str("#[serde(tag = \"type\")] enum E { A, B(Inner) }").unwrap();
+        let e = SimpleEnum::from_syn_type(&e).unwrap();
+        assert_eq!(
+            e.to_ts(&Options::default()),
+            "export type E =\n  { type: \"A\" } |\n  { type: \"B\" } & Inner;\n"
+        );
+    }
+
+    #[test]
+    fn adjacently_tagged_enum() {
+        let e: syn::ItemEnum = syn::parse_str(
+            "#[serde(tag = \"t\", content = \"c\")] enum E { A, B(i32), C(i32, String) }",
+        )
+        .unwrap();
+        let e = SimpleEnum::from_syn_type(&e).unwrap();
+        assert_eq!(
+            e.to_ts(&Options::default()),
+            "export type E =\n  { t: \"A\" } |\n  { t: \"B\"; c: number } |\n  \
+             { t: \"C\"; c: [number, string] };\n"
+        );
+    }
+
+    #[test]
+    fn untagged_enum() {
+        let e: syn::ItemEnum =
+            syn::parse_str("#[serde(untagged)] enum E { A, B(i32), C(String, i32) }").unwrap();
+        let e = SimpleEnum::from_syn_type(&e).unwrap();
+        assert_eq!(
+            e.to_ts(&Options::default()),
+            "export type E =\n  null |\n  number |\n  [string, number];\n"
+        );
+    }
+
+    #[test]
+    fn skip_fields() {
+        let s: syn::ItemStruct = syn::parse_str(
+            "#[derive(Serialize)]
+             struct X {
+                 #[serde(skip)] a: i32,
+                 #[serde(skip_serializing)] b: i32,
+                 #[serde(skip_deserializing)] c: i32,
+             }",
+        )
+        .unwrap();
+        let s = SimpleStruct::new(&s).unwrap();
+        let mut opts = Options::default();
+        assert_eq!(
+            s.to_ts(&opts),
+            "export interface X {\n  b?: number;\n  c?: number;\n}\n"
+        );
+        opts.direction = Direction::Serialize;
+        assert_eq!(s.to_ts(&opts), "export interface X {\n  c: number;\n}\n");
+        opts.direction = Direction::Deserialize;
+        assert_eq!(s.to_ts(&opts), "export interface X {\n  b: number;\n}\n");
+    }
+
+    #[test]
+    fn skip_serializing_if() {
+        let s: syn::ItemStruct = syn::parse_str(
+            "#[derive(Serialize)]
+             struct X {
+                 #[serde(skip_serializing_if = \"Option::is_none\")] a: Option<i32>,
+                 #[serde(skip_serializing_if = \"Vec::is_empty\")] b: Vec<i32>,
+             }",
+        )
+        .unwrap();
+        let s = SimpleStruct::new(&s).unwrap();
+        let mut opts = Options::default();
+        assert_eq!(
+            s.to_ts(&opts),
+            "export interface X {\n  a?: number | null;\n  b?: number[];\n}\n"
+        );
+        opts.collapse_option = true;
+        assert_eq!(
+            s.to_ts(&opts),
+            "export interface X {\n  a?: number;\n  b?: number[];\n}\n"
+        );
+        opts.direction = Direction::Deserialize;
+        assert_eq!(
+            s.to_ts(&opts),
+            "export interface X {\n  a: number | null;\n  b: number[];\n}\n"
+        );
+    }
+
+    #[test]
+    fn default_fields() {
+        let s: syn::ItemStruct = syn::parse_str(
+            "#[derive(Deserialize)]
+             struct X { #[serde(default)] a: i32, #[serde(default = \"one\")] b: i32, c: i32 }",
+        )
+        .unwrap();
+        let s = SimpleStruct::new(&s).unwrap();
+        let mut opts = Options {
+            direction: Direction::Deserialize,
+            ..Default::default()
+        };
+        assert_eq!(
+            s.to_ts(&opts),
+            "export interface X {\n  a?: number;\n  b?: number;\n  c: number;\n}\n"
+        );
+        opts.direction = Direction::Serialize;
+        assert_eq!(
+            s.to_ts(&opts),
+            "export interface X {\n  a: number;\n  b: number;\n  c: number;\n}\n"
+        );
+
+        let s: syn::ItemStruct =
+            syn::parse_str("#[derive(Deserialize)] #[serde(default)] struct X { a: i32 }").unwrap();
+        let s = SimpleStruct::new(&s).unwrap();
+        assert_eq!(
+            s.to_ts(&Options::default()),
+            "export interface X {\n  a?: number;\n}\n"
+        );
+    }
+
+    #[test]
+    fn flatten() {
+        let s: syn::ItemStruct = syn::parse_str(
+            "#[derive(Serialize)]
+             struct X { a: i32, #[serde(flatten)] b: B, #[serde(flatten)] c: C }",
+        )
+        .unwrap();
+        let s = SimpleStruct::new(&s).unwrap();
+        let mut opts = Options::default();
+        opts.interfaces.insert("B".to_string());
+        assert_eq!(
+            s.to_ts(&opts),
+            "export type X = B & C & {\n  a: number;\n};\n"
+        );
+        opts.interfaces.insert("C".to_string());
+        assert_eq!(
+            s.to_ts(&opts),
+            "export interface X extends B, C {\n  a: number;\n}\n"
+        );
+    }
+
+    #[test]
+    fn flatten_map() {
+        let s: syn::ItemStruct = syn::parse_str(
+            "#[derive(Serialize)]
+             struct X { a: i32, #[serde(flatten)] extra: HashMap<String, Value> }",
+        )
+        .unwrap();
+        let s = SimpleStruct::new(&s).unwrap();
+        assert_eq!(
+            s.to_ts(&Options::default()),
+            "export interface X {\n  a: number;\n  [key: string]: unknown;\n}\n"
+        );
+    }
+
+    #[test]
+    fn aliases() {
+        let s: syn::ItemStruct = syn::parse_str(
+            "#[derive(Deserialize)]
+             struct X { #[serde(alias = \"b\", alias = \"c\")] a: i32 }",
+        )
+        .unwrap();
+        let s = SimpleStruct::new(&s).unwrap();
+        assert_eq!(
+            s.to_ts(&Options::default()),
+            "export interface X {\n  /** Also accepted as \"b\", \"c\" */\n  a: number;\n}\n"
+        );
+
+        let e: syn::ItemEnum = syn::parse_str("enum E { #[serde(alias = \"b\")] A }").unwrap();
+        let e = SimpleEnum::from_syn_type(&e).unwrap();
+        let opts = Options {
+            direction: Direction::Serialize,
+            ..Default::default()
+        };
+        assert_eq!(e.to_ts(&opts), "export type E =\n  \"A\";\n");
+    }
+
+    #[test]
+    fn transparent() {
+        let s: syn::ItemStruct = syn::parse_str(
+            "#[derive(Serialize)] #[serde(transparent)]
+             struct X { #[serde(skip)] a: i32, b: String }",
+        )
+        .unwrap();
+        let s = SimpleStruct::new(&s).unwrap();
+        assert_eq!(s.to_ts(&Options::default()), "export type X = string;\n");
+    }
+
+    #[test]
+    fn other_variant() {
+        let e: syn::ItemEnum = syn::parse_str("enum E { A, #[serde(other)] Unknown }").unwrap();
+        let e = SimpleEnum::from_syn_type(&e).unwrap();
+        assert_eq!(
+            e.to_ts(&Options::default()),
+            "export type E =\n  \"A\" |\n  \"Unknown\" |\n  (string & {});\n"
+        );
+
+        let e: syn::ItemEnum =
+            syn::parse_str("#[serde(tag = \"t\")] enum E { A, #[serde(other)] Unknown }").unwrap();
+        let e = SimpleEnum::from_syn_type(&e).unwrap();
+        let opts = Options {
+            other_fallback: "string".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            e.to_ts(&opts),
+            "export type E =\n  { t: \"A\" } |\n  { t: \"Unknown\" } |\n  { t: string };\n"
+        );
+    }
+
+    #[test]
+    fn repr_enum() {
+        let e: syn::ItemEnum =
+            syn::parse_str("#[derive(Serialize_repr)] #[repr(i8)] enum E { A = -1, B, C = 5, D }")
+                .unwrap();
+        let e = SimpleEnum::from_syn_type(&e).unwrap();
+        assert_eq!(
+            e.to_ts(&Options::default()),
+            "export type E =\n  -1 |\n  0 |\n  5 |\n  6;\n"
+        );
+    }
+
+    #[test]
+    fn deny_unknown_fields() {
+        let s: syn::ItemStruct = syn::parse_str(
+            "#[derive(Deserialize)] #[serde(deny_unknown_fields)] struct X { a: i32 }",
+        )
+        .unwrap();
+        let s = SimpleStruct::new(&s).unwrap();
+        assert_eq!(
+            s.to_ts(&Options::default()),
+            "/** Unknown fields are rejected */\nexport interface X {\n  a: number;\n}\n"
+        );
+    }
+
+    #[test]
+    fn with_type() {
+        let s: syn::ItemStruct = syn::parse_str(
+            "#[derive(Serialize)]
+             struct X { #[serde(with = \"ts_seconds\")] a: DateTime<Utc> }",
+        )
+        .unwrap();
+        let s = SimpleStruct::new(&s).unwrap();
+        let mut opts = Options::default();
+        opts.with_types
+            .insert("ts_seconds".to_string(), "number".to_string());
+        assert_eq!(s.to_ts(&opts), "export interface X {\n  a: number;\n}\n");
+
+        // Newtype and transparent fields are just their value
+        let s: syn::ItemStruct = syn::parse_str(
+            "#[derive(Serialize)]
+             struct Stamp(#[serde(with = \"chrono::serde::ts_seconds\")] DateTime<Utc>);",
+        )
+        .unwrap();
+        let s = SimpleStruct::new(&s).unwrap();
+        assert_eq!(s.to_ts(&opts), "export type Stamp = number;\n");
+        let s: syn::ItemStruct = syn::parse_str(
+            "#[derive(Serialize)] #[serde(transparent)]
+             struct Stamp { #[serde(with = \"ts_seconds\")] at: DateTime<Utc> }",
+        )
+        .unwrap();
+        let s = SimpleStruct::new(&s).unwrap();
+        assert_eq!(s.to_ts(&opts), "export type Stamp = number;\n");
+
+        assert_eq!(
+            split_mapping("a::b = c"),
+            Some(("a::b".to_string(), "c".to_string()))
+        );
+        assert_eq!(split_mapping("a::b"), None);
+    }
+
+    #[test]
+    fn type_override() {
+        let s: syn::ItemStruct = syn::parse_str(
+            "#[derive(Serialize)]
+             struct X {
+                 #[serde(serialize_with = \"as_string\")] #[rsts(type = \"string\")] a: i32,
+             }",
+        )
+        .unwrap();
+        let s = SimpleStruct::new(&s).unwrap();
+        assert_eq!(
+            s.to_ts(&Options::default()),
+            "export interface X {\n  a: string;\n}\n"
+        );
+
+        let s: syn::ItemStruct =
+            syn::parse_str("#[derive(Serialize)] struct Id(#[rsts(type = \"string\")] u64);")
+                .unwrap();
+        let s = SimpleStruct::new(&s).unwrap();
+        assert_eq!(s.to_ts(&Options::default()), "export type Id = string;\n");
+
+        let e: syn::ItemEnum = syn::parse_str(
+            "#[derive(Serialize)]
+             enum E {
+                 A(#[rsts(type = \"string\")] u64),
+                 B(#[rsts(type = \"string\")] u64, u8),
+             }",
+        )
+        .unwrap();
+        let e = SimpleEnum::from_syn_type(&e).unwrap();
+        assert_eq!(
+            e.to_ts(&Options::default()),
+            "export type E =\n  { A: string } |\n  { B: [string, number] };\n"
+        );
+    }
+
+    #[test]
+    fn bounds() {
+        let s: syn::ItemStruct = syn::parse_str(
+            "#[derive(Serialize)]
+             #[serde(bound(serialize = \"T: Serialize\"), rename_all = \"camelCase\")]
+             struct X<T: Clone> where T: Default {
+                 #[serde(bound = \"T: Serialize\")] some_item: T,
+             }",
+        )
+        .unwrap();
+        let s = SimpleStruct::new(&s).unwrap();
+        assert_eq!(
+            s.to_ts(&Options::default()),
+            "export interface X<T> {\n  someItem: T;\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_attr_to_derives() {
+        let s: syn::ItemStruct = syn::parse_str("#[derive(A, B)] struct X {}").unwrap();
+        assert_eq!(
+            attr_to_derives(&s.attrs[0]),
+            vec!["A".to_string(), "B".to_string()]
+        );
+    }
+
+    #[test]
+    fn cfg_attr_derives() {
+        let s: syn::ItemStruct = syn::parse_str(
+            "#[cfg_attr(feature = \"serde\", derive(Serialize, Deserialize))]
+             #[cfg_attr(feature = \"serde\", serde(rename_all = \"camelCase\"))]
+             struct X { some_field: i32 }",
+        )
+        .unwrap();
+        assert_eq!(
+            attr_to_derives(&s.attrs[0]),
+            vec!["Serialize".to_string(), "Deserialize".to_string()]
+        );
+        let s = SimpleStruct::new(&s).unwrap();
+        assert_eq!(
+            s.to_ts(&Options::default()),
+            "export interface X {\n  someField: number;\n}\n"
+        );
+    }
+
+    #[test]
+    fn path_derives() {
+        let s: syn::ItemStruct = syn::parse_str(
+            "#[derive(Debug, serde::Serialize)]
+             #[cfg_attr(test, derive(my::Ser))]
+             struct X {}",
+        )
+        .unwrap();
+        let mut opts = Options::default();
+        assert!(derives_serde(&s.attrs, &opts));
+        assert!(!derives_serde(&s.attrs[1..], &opts));
+        opts.derive_aliases.push("Ser".to_string());
+        assert!(derives_serde(&s.attrs[1..], &opts));
+    }
+
+    #[test]
+    fn untagged_variant() {
+        let e: syn::ItemEnum =
+            syn::parse_str("enum E { A(i32), #[serde(untagged)] B(String) }").unwrap();
+        let e = SimpleEnum::from_syn_type(&e).unwrap();
+        assert_eq!(
+            e.to_ts(&Options::default()),
+            "export type E =\n  { A: number } |\n  string;\n"
+        );
+    }
+
+    #[test]
+    fn enum_rename_all_fields() {
+        let e: syn::ItemEnum = syn::parse_str(
+            "#[serde(tag = \"type\", rename_all_fields = \"camelCase\")]
+             enum E {
+                 Move { x_pos: i32 },
+                 #[serde(rename_all = \"UPPERCASE\")] Jump { y_pos: i32 },
+             }",
+        )
+        .unwrap();
+        let e = SimpleEnum::from_syn_type(&e).unwrap();
+        assert_eq!(
+            e.to_ts(&Options::default()),
+            "export type E =\n  { type: \"Move\"; xPos: number } |\n  \
+             { type: \"Jump\"; Y_POS: number };\n"
+        );
+    }
+
+    #[test]
+    fn skip_variants() {
+        let e: syn::ItemEnum =
+            syn::parse_str("enum E { A, #[serde(skip)] B, #[serde(skip_serializing)] C }").unwrap();
+        let e = SimpleEnum::from_syn_type(&e).unwrap();
+        assert_eq!(
+            e.to_ts(&Options::default()),
+            "export type E =\n  \"A\" |\n  \"C\";\n"
+        );
+        let opts = Options {
+            direction: Direction::Serialize,
+            ..Default::default()
+        };
+        assert_eq!(e.to_ts(&opts), "export type E =\n  \"A\";\n");
+    }
+
+    #[test]
+    fn chrono_with() {
+        let s: syn::ItemStruct = syn::parse_str(
+            "#[derive(Serialize)]
+             struct X {
+                 #[serde(with = \"chrono::serde::ts_seconds\")] a: DateTime<Utc>,
+                 #[serde(with = \"ts_milliseconds_option\")] b: Option<DateTime<Utc>>,
+             }",
+        )
+        .unwrap();
+        let s = SimpleStruct::new(&s).unwrap();
+        assert_eq!(
+            s.to_ts(&Options::default()),
+            "export interface X {\n  a: number;\n  b: number | null;\n}\n"
+        );
+    }
+
+    #[test]
+    fn fixed_size_array() {
+        let ty: syn::Type = syn::parse_str("[f64; 3]").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(st.to_ts(&Options::default()), "[number, number, number]");
+
+        let ty: syn::Type = syn::parse_str("[Option<u8>; 16]").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(st.to_ts(&Options::default()), "(number | null)[]");
+
+        let ty: syn::Type = syn::parse_str("[u8; N]").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(st.to_ts(&Options::default()), "number[]");
+    }
+
+    #[test]
+    fn references() {
+        let ty: syn::Type = syn::parse_str("&'a str").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(st.to_ts(&Options::default()), "string");
+
+        let ty: syn::Type = syn::parse_str("&Option<&[u8]>").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(st.to_ts(&Options::default()), "number[] | null");
+    }
+
+    #[test]
+    fn smart_pointers() {
+        let ty: syn::Type = syn::parse_str("Box<Option<Arc<Rc<String>>>>").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(st.to_ts(&Options::default()), "string | null");
+    }
+
+    #[test]
+    fn cow() {
+        let ty: syn::Type = syn::parse_str("Cow<'a, str>").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(st.to_ts(&Options::default()), "string");
+
+        let ty: syn::Type = syn::parse_str("Cow<'static, [u32]>").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(st.to_ts(&Options::default()), "number[]");
+    }
+
+    #[test]
+    fn sets() {
+        let ty: syn::Type = syn::parse_str("HashSet<String>").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(st.to_ts(&Options::default()), "string[]");
+
+        let ty: syn::Type = syn::parse_str("BTreeSet<u8>").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(st.to_ts(&Options::default()), "number[]");
+    }
+
+    #[test]
+    fn btreemap() {
+        let ty: syn::Type = syn::parse_str("BTreeMap<String, Vec<i32>>").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(st.to_ts(&Options::default()), "Record<string, number[]>");
+    }
+
+    #[test]
+    fn deque_and_list() {
+        let ty: syn::Type = syn::parse_str("VecDeque<String>").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(st.to_ts(&Options::default()), "string[]");
+
+        let ty: syn::Type = syn::parse_str("LinkedList<Option<bool>>").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(st.to_ts(&Options::default()), "(boolean | null)[]");
+    }
+
+    #[test]
+    fn indexmap() {
+        let ty: syn::Type = syn::parse_str("IndexMap<String, IndexSet<i32>>").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(st.to_ts(&Options::default()), "Record<string, number[]>");
+    }
+
+    #[test]
+    fn result() {
+        let ty: syn::Type = syn::parse_str("Vec<Result<i32, String>>").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(
+            st.to_ts(&Options::default()),
+            "({ Ok: number } | { Err: string })[]"
+        );
+    }
+
+    #[test]
+    fn json_value() {
+        let ty: syn::Type = syn::parse_str("serde_json::Value").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(st.to_ts(&Options::default()), "unknown");
+
+        let ty: syn::Type = syn::parse_str("Map<String, Value>").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        let opts = Options {
+            json_value: "JsonValue".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(st.to_ts(&opts), "Record<string, JsonValue>");
+    }
+
+    #[test]
+    fn nested_option() {
+        let s: syn::ItemStruct = syn::parse_str(
+            "#[derive(Serialize)]
+             struct X {
+                 #[serde(default, skip_serializing_if = \"Option::is_none\")]
+                 a: Option<Option<i32>>,
+             }",
+        )
+        .unwrap();
+        let s = SimpleStruct::new(&s).unwrap();
+        assert_eq!(
+            s.to_ts(&Options::default()),
+            "export interface X {\n  a?: number | null;\n}\n"
+        );
+    }
+
+    #[test]
+    fn uuid() {
+        let st = SimpleType::new(vec!["Uuid".to_string()], vec![]);
+        assert_eq!(st.to_ts(&Options::default()), "string");
+        let opts = Options {
+            uuid_alias: true,
+            ..Default::default()
+        };
+        assert_eq!(st.to_ts(&opts), "Uuid");
+        assert!(preamble(&opts, "").contains("export type Uuid = "));
+    }
+
+    #[test]
+    fn url() {
+        let st = SimpleType::new(vec!["Url".to_string()], vec![]);
+        assert_eq!(st.to_ts(&Options::default()), "string");
+        let opts = Options {
+            url_alias: true,
+            ..Default::default()
+        };
+        assert_eq!(st.to_ts(&opts), "Url");
+        assert!(preamble(&opts, "").contains("export type Url = string;"));
+    }
+
+    #[test]
+    fn paths() {
+        let ty: syn::Type = syn::parse_str("&Path").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(st.to_ts(&Options::default()), "string");
+
+        let ty: syn::Type = syn::parse_str("Vec<Utf8PathBuf>").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(st.to_ts(&Options::default()), "string[]");
+    }
+
+    #[test]
+    fn network_addresses() {
+        for name in ["IpAddr", "Ipv4Addr", "Ipv6Addr", "SocketAddr"].iter() {
+            let st = SimpleType::new(vec![name.to_string()], vec![]);
+            assert_eq!(st.to_ts(&Options::default()), "string");
+        }
+    }
+
+    #[test]
+    fn nonzero() {
+        let st = SimpleType::new(vec!["NonZeroU32".to_string()], vec![]);
+        assert_eq!(st.to_ts(&Options::default()), "number");
+        let ty: syn::Type = syn::parse_str("NonZero<i128>").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        let opts = Options {
+            int128: BigIntType::String,
+            ..Default::default()
+        };
+        assert_eq!(st.to_ts(&opts), "string");
+        let st = SimpleType::new(vec!["NonZeroThing".to_string()], vec![]);
+        assert_eq!(st.to_ts(&Options::default()), "NonZeroThing");
+    }
+
+    #[test]
+    fn int64() {
+        let st = SimpleType::new(vec!["u64".to_string()], vec![]);
+        assert_eq!(st.to_ts(&Options::default()), "number");
+        let opts = Options {
+            int64: BigIntType::String,
+            ..Default::default()
+        };
+        assert_eq!(st.to_ts(&opts), "string");
+        let st = SimpleType::new(vec!["NonZeroI64".to_string()], vec![]);
+        assert_eq!(st.to_ts(&opts), "string");
+    }
+
+    #[test]
+    fn decimal() {
+        let st = SimpleType::new(vec!["Decimal".to_string()], vec![]);
+        assert_eq!(st.to_ts(&Options::default()), "string");
+        let opts = Options {
+            decimal: "number".to_string(),
+            ..Default::default()
+        };
+        let st = SimpleType::new(vec!["BigDecimal".to_string()], vec![]);
+        assert_eq!(st.to_ts(&opts), "number");
+    }
+
+    #[test]
+    fn naive_dates() {
+        let st = SimpleType::new(vec!["NaiveDate".to_string()], vec![]);
+        assert_eq!(st.to_ts(&Options::default()), "string");
+        let opts = Options {
+            date_aliases: true,
+            ..Default::default()
+        };
+        assert_eq!(st.to_ts(&opts), "IsoDate");
+        let st = SimpleType::new(vec!["NaiveTime".to_string()], vec![]);
+        assert_eq!(st.to_ts(&opts), "IsoTime");
+        assert!(preamble(&opts, "").contains("export type IsoDateTime = string;"));
+    }
+
+    #[test]
+    fn datetime_offsets() {
+        let ty: syn::Type = syn::parse_str("DateTime<FixedOffset>").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(st.to_ts(&Options::default()), "DateTimeUtc");
+        let mut opts = Options::default();
+        opts.datetime_types
+            .insert("FixedOffset".to_string(), "string".to_string());
+        assert_eq!(st.to_ts(&opts), "string");
+        let ty: syn::Type = syn::parse_str("DateTime<Local>").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(st.to_ts(&opts), "DateTimeUtc");
+    }
+
+    #[test]
+    fn time_crate() {
+        let st = SimpleType::new(vec!["OffsetDateTime".to_string()], vec![]);
+        assert_eq!(st.to_ts(&Options::default()), "string");
+        let ty: syn::Type = syn::parse_str("time::Date").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(st.to_ts(&Options::default()), "string");
+        let mut opts = Options::default();
+        opts.time_types
+            .insert("Date".to_string(), "number".to_string());
+        assert_eq!(st.to_ts(&opts), "number");
+
+        assert_eq!(
+            known_with_type("time::serde::rfc3339::option"),
+            Some("string | null".to_string())
+        );
+        assert_eq!(
+            known_with_type("time::serde::timestamp"),
+            Some("number".to_string())
+        );
+    }
+
+    #[test]
+    fn std_time() {
+        let ty: syn::Type = syn::parse_str("std::time::Duration").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(
+            st.to_ts(&Options::default()),
+            "{ secs: number; nanos: number }"
+        );
+        let st = SimpleType::new(vec!["SystemTime".to_string()], vec![]);
+        assert_eq!(
+            st.to_ts(&Options::default()),
+            "{ secs_since_epoch: number; nanos_since_epoch: number }"
+        );
+        assert_eq!(
+            known_with_type("humantime_serde"),
+            Some("string".to_string())
+        );
+
+        // A Duration defined in the input isn't std's
+        let mut opts = Options::default();
+        opts.defined_types.insert("Duration".to_string());
+        let st = SimpleType::new(vec!["Duration".to_string()], vec![]);
+        assert_eq!(st.to_ts(&opts), "Duration");
+        let ty: syn::Type = syn::parse_str("std::time::Duration").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(st.to_ts(&opts), "{ secs: number; nanos: number }");
+    }
+
+    #[test]
+    fn chrono_duration() {
+        let ty: syn::Type = syn::parse_str("chrono::Duration").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(st.to_ts(&Options::default()), "string");
+        let opts = Options {
+            chrono_duration: "number".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(st.to_ts(&opts), "number");
+        // A bare Duration is std's
+        let st = SimpleType::new(vec!["Duration".to_string()], vec![]);
+        assert_eq!(st.to_ts(&opts), "{ secs: number; nanos: number }");
+    }
+
+    #[test]
+    fn bytes() {
+        let s: syn::ItemStruct = syn::parse_str(
+            "#[derive(Serialize)]
+             struct X {
+                 a: Bytes,
+                 #[serde(with = \"serde_bytes\")] b: Vec<u8>,
+                 #[serde(with = \"serde_bytes\")] c: Option<Vec<u8>>,
+             }",
+        )
+        .unwrap();
+        let s = SimpleStruct::new(&s).unwrap();
+        assert_eq!(
+            s.to_ts(&Options::default()),
+            "export interface X {\n  a: number[];\n  b: number[];\n  c: number[] | null;\n}\n"
+        );
+        let opts = Options {
+            bytes: "string".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            s.to_ts(&opts),
+            "export interface X {\n  a: string;\n  b: string;\n  c: string | null;\n}\n"
+        );
+    }
+
+    #[test]
+    fn either() {
+        let ty: syn::Type = syn::parse_str("either::Either<i32, String>").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(
+            st.to_ts(&Options::default()),
+            "{ Left: number } | { Right: string }"
+        );
+    }
+
+    #[test]
+    fn phantom_data() {
+        let s: syn::ItemStruct = syn::parse_str(
+            "#[derive(Serialize)]
+             struct X<T> { a: i32, _marker: std::marker::PhantomData<T> }",
+        )
+        .unwrap();
+        let s = SimpleStruct::new(&s).unwrap();
+        assert_eq!(
+            s.to_ts(&Options::default()),
+            "export interface X<T> {\n  a: number;\n}\n"
+        );
+    }
+
+    #[test]
+    fn range() {
+        let ty: syn::Type = syn::parse_str("std::ops::RangeInclusive<u32>").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(
+            st.to_ts(&Options::default()),
+            "{ start: number; end: number }"
+        );
+    }
+
+    #[test]
+    fn wrapping() {
+        let ty: syn::Type = syn::parse_str("Wrapping<u64>").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        let opts = Options {
+            int64: BigIntType::String,
+            ..Default::default()
+        };
+        assert_eq!(st.to_ts(&opts), "string");
+    }
+
+    #[test]
+    fn bitflags() {
+        let opts = Options {
+            bitflags: vec!["Flags".to_string()],
+            ..Default::default()
+        };
+        let st = SimpleType::new(vec!["Flags".to_string()], vec![]);
+        assert_eq!(st.to_ts(&opts), "number");
+
+        let mac: BitflagsMacro = syn::parse_str(
+            "#[derive(Serialize)]
+             pub struct Flags: u32 {
+                 const A = 0b01;
+                 const B = 1 << 1;
+                 const AB = Self::A.bits() | Self::B.bits();
+                 const _ = !0;
+             }",
+        )
+        .unwrap();
+        assert_eq!(
+            mac.types[0].to_ts(),
+            "export const Flags = {\n  A: 1,\n  B: 2,\n  AB: 3,\n} as const;\n"
+        );
+
+        // Types from bitflags! in the input don't need --bitflags
+        let file: syn::File = syn::parse_str(
+            "bitflags! { struct Perms: u8 { const R = 1; } }
+             #[derive(Serialize)] struct File { perms: Perms }",
+        )
+        .unwrap();
+        let f = SimpleFile::from_items("a.rs".to_string(), file.items, &Options::default());
+        let mut opts = Options::default();
+        f.register_interfaces(&mut opts);
+        assert_eq!(
+            f.structs[0].to_ts(&opts),
+            "export interface File {\n  perms: number;\n}\n"
+        );
+    }
+
+    #[test]
+    fn semver() {
+        let s: syn::ItemStruct = syn::parse_str(
+            "#[derive(Serialize)] struct Release { version: Version, requires: VersionReq }",
+        )
+        .unwrap();
+        let s = SimpleStruct::new(&s).unwrap();
+        assert_eq!(
+            s.to_ts(&Options::default()),
+            "export interface Release {\n  version: string;\n  requires: string;\n}\n"
+        );
+
+        // An input type named Version is used instead
+        let file: syn::File = syn::parse_str(
+            "#[derive(Serialize)] struct Version { major: u32, minor: u32 }
+             #[derive(Serialize)] struct Release { version: Version, semver: semver::Version }",
+        )
+        .unwrap();
+        let f = SimpleFile::from_items("a.rs".to_string(), file.items, &Options::default());
+        let mut opts = Options::default();
+        f.register_interfaces(&mut opts);
+        assert_eq!(
+            f.structs[1].to_ts(&opts),
+            "export interface Release {\n  version: Version;\n  semver: string;\n}\n"
+        );
+    }
+
+    #[test]
+    fn bson() {
+        let ty: syn::Type = syn::parse_str("bson::oid::ObjectId").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(st.to_ts(&Options::default()), "{ $oid: string }");
+        let ty: syn::Type = syn::parse_str("bson::DateTime").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(
+            st.to_ts(&Options::default()),
+            "{ $date: { $numberLong: string } }"
+        );
+        let opts = Options {
+            bson_plain: true,
+            ..Default::default()
+        };
+        assert_eq!(st.to_ts(&opts), "string");
+
+        let file: syn::File = syn::parse_str(
+            "type DateTime = chrono::DateTime<Utc>;
+             #[derive(Serialize)] struct Event { at: DateTime, id: bson::oid::ObjectId }",
+        )
+        .unwrap();
+        let f = SimpleFile::from_items("a.rs".to_string(), file.items, &Options::default());
+        let mut opts = Options::default();
+        f.register_interfaces(&mut opts);
+        assert_eq!(
+            f.structs[0].to_ts(&opts),
+            "export interface Event {\n  at: DateTime;\n  id: { $oid: string };\n}\n"
+        );
+    }
+
+    #[test]
+    fn fixed_capacity_vecs() {
+        for ty in &[
+            "SmallVec<[u8; 16]>",
+            "ArrayVec<u8, 16>",
+            "heapless::Vec<u8, 16>",
+            "Vec<u8, N>",
+        ] {
+            let ty: syn::Type = syn::parse_str(ty).unwrap();
+            let st = SimpleType::from_syn_type(&ty).unwrap();
+            assert_eq!(st.to_ts(&Options::default()), "number[]");
+        }
+    }
+
+    #[test]
+    fn small_strings() {
+        let ty: syn::Type = syn::parse_str("SmartString<LazyCompact>").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(st.to_ts(&Options::default()), "string");
+        let ty: syn::Type = syn::parse_str("ArrayString<16>").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(st.to_ts(&Options::default()), "string");
+        // Too generic a name to assume
+        let st = SimpleType::new(vec!["Str".to_string()], vec![]);
+        assert_eq!(st.to_ts(&Options::default()), "Str");
+        let st = SimpleType::new(vec!["Name".to_string()], vec![]);
+        let opts = Options {
+            string_types: vec!["Name".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(st.to_ts(&opts), "string");
+    }
+
+    #[test]
+    fn wrapper_types() {
+        let ty: syn::Type = syn::parse_str("OrderedFloat<f64>").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(st.to_ts(&Options::default()), "number");
+        let ty: syn::Type = syn::parse_str("Tracked<String>").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        let opts = Options {
+            wrapper_types: vec!["Tracked".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(st.to_ts(&opts), "string");
+    }
+
+    #[test]
+    fn qualified_paths() {
+        let ty: syn::Type =
+            syn::parse_str("std::collections::HashMap<std::string::String, i32>").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(st.to_ts(&Options::default()), "Record<string, number>");
+        let ty: syn::Type = syn::parse_str("chrono::DateTime<chrono::Utc>").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(st.to_ts(&Options::default()), "DateTimeUtc");
+        let ty: syn::Type = syn::parse_str("Option<crate::models::User>").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(st.to_ts(&Options::default()), "User | null");
+    }
+
+    #[test]
+    fn unwrap_containers() {
+        let ty: syn::Type = syn::parse_str("Arc<Mutex<Vec<i32>>>").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(st.to_ts(&Options::default()), "number[]");
+        let ty: syn::Type = syn::parse_str("state::Shared<bool>").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        let opts = Options {
+            wrapper_types: vec!["Shared".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(st.to_ts(&opts), "boolean");
+    }
+
+    #[test]
+    fn trait_objects() {
+        let ty: syn::Type = syn::parse_str("Box<dyn std::error::Error + Send + Sync>").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(st.to_ts(&Options::default()), "unknown");
+        assert_eq!(st.unmapped_traits(&Options::default()), vec!["Error"]);
+        let mut opts = Options::default();
+        opts.trait_types
+            .insert("Error".to_string(), "string".to_string());
+        assert_eq!(st.to_ts(&opts), "string");
+        assert!(st.unmapped_traits(&opts).is_empty());
+    }
+
+    #[test]
+    fn unrepresentable_types() {
+        let err = |ty| {
+            let ty: syn::Type = syn::parse_str(ty).unwrap();
+            SimpleType::from_syn_type(&ty).unwrap_err().to_string()
+        };
+        assert_eq!(
+            err("impl Display"),
+            "`impl Trait` has no concrete type to convert"
+        );
+        assert_eq!(
+            err("fn(i32) -> i32"),
+            "function pointers can't be serialized"
+        );
+        assert_eq!(err("*const u8"), "raw pointers can't be serialized");
+    }
+
+    #[test]
+    fn lifetimes() {
+        let s: syn::ItemStruct = syn::parse_str(
+            "#[derive(Serialize)]
+             struct X<'a, 'b: 'a> {
+                 a: &'a str,
+                 b: Cow<'b, [u8]>,
+                 c: Option<&'a Inner<'b>>,
+                 d: Box<dyn Display + 'a>,
+             }",
+        )
+        .unwrap();
+        let s = SimpleStruct::new(&s).unwrap();
+        assert_eq!(
+            s.to_ts(&Options::default()),
+            "export interface X {\n  a: string;\n  b: number[];\n  c: Inner | null;\n  \
+             d: unknown;\n}\n"
+        );
+    }
+
+    #[test]
+    fn const_generics() {
+        let ty: syn::Type = syn::parse_str("Matrix<3, { N }>").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert!(st.generic_args.is_empty());
+        assert_eq!(st.const_args, vec!["3", "N"]);
+        assert_eq!(st.to_ts(&Options::default()), "Matrix");
+
+        let ty: syn::Type = syn::parse_str("Matrix<f64, ROWS>").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(st.const_args, vec!["ROWS"]);
+        assert_eq!(st.to_ts(&Options::default()), "Matrix<number>");
+
+        let ty: syn::Type = syn::parse_str("ArrayVec<u8, { CAP + 1 }>").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(st.const_args, vec!["CAP + 1"]);
+        assert_eq!(st.to_ts(&Options::default()), "number[]");
+
+        let ty: syn::Type = syn::parse_str("[u8; N]").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(st.array_len, Some("N".to_string()));
+        assert_eq!(st.to_ts(&Options::default()), "number[]");
+    }
+
+    #[test]
+    fn generic_struct() {
+        let s: syn::ItemStruct = syn::parse_str(
+            "#[derive(Serialize)] struct Paginated<T> { items: Vec<T>, total: u64 }",
+        )
+        .unwrap();
+        let s = SimpleStruct::new(&s).unwrap();
+        assert_eq!(
+            s.to_ts(&Options::default()),
+            "export interface Paginated<T> {\n  items: T[];\n  total: number;\n}\n"
+        );
+
+        let ty: syn::Type = syn::parse_str("Paginated<Option<User>>").unwrap();
+        let st = SimpleType::from_syn_type(&ty).unwrap();
+        assert_eq!(st.to_ts(&Options::default()), "Paginated<User | null>");
+    }
+
+    #[test]
+    fn generic_enum() {
+        let e: syn::ItemEnum =
+            syn::parse_str("enum ApiResult<T, E> { Success(T), Failure { error: E } }").unwrap();
+        let e = SimpleEnum::from_syn_type(&e).unwrap();
+        assert_eq!(
+            e.to_ts(&Options::default()),
+            "export type ApiResult<T, E> =\n  { Success: T } |\n  { Failure: { error: E } };\n"
+        );
+    }
+
+    #[test]
+    fn generic_bounds() {
+        let s: syn::ItemStruct = syn::parse_str(
+            "#[derive(Serialize)]
+             struct X<T: Serialize + Clone, U> where U: Debug { a: T, b: U }",
+        )
+        .unwrap();
+        let s = SimpleStruct::new(&s).unwrap();
+        assert_eq!(
+            s.to_ts(&Options::default()),
+            "export interface X<T, U> {\n  a: T;\n  b: U;\n}\n"
+        );
+        let e: syn::ItemEnum =
+            syn::parse_str("enum E<T> where T: Serialize + 'static { A(T) }").unwrap();
+        let e = SimpleEnum::from_syn_type(&e).unwrap();
+        assert_eq!(
+            e.to_ts(&Options::default()),
+            "export type E<T> =\n  { A: T };\n"
+        );
+    }
+
+    #[test]
+    fn default_type_params() {
+        let s: syn::ItemStruct =
+            syn::parse_str("#[derive(Serialize)] struct Response<T = ()> { data: T }").unwrap();
+        let s = SimpleStruct::new(&s).unwrap();
+        assert_eq!(
+            s.to_ts(&Options::default()),
+            "export interface Response<T = null> {\n  data: T;\n}\n"
+        );
+        let opts = Options {
+            unit_type: "undefined".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            s.to_ts(&opts),
+            "export interface Response<T = undefined> {\n  data: T;\n}\n"
+        );
+    }
+
+    #[test]
+    fn monomorphization() {
+        let parse = |src| SimpleStruct::new(&syn::parse_str(src).unwrap()).unwrap();
+        let mut files = vec![SimpleFile {
+            name: "a.rs".to_string(),
+            enums: vec![],
+            structs: vec![
+                parse("struct Paginated<T> { items: Vec<T>, next: Option<Cursor<T>> }"),
+                parse("struct Cursor<T> { last: T }"),
+                parse("struct Users { page: Paginated<User> }"),
+            ],
+            bitflags: vec![],
+            aliases: vec![],
+            consts: vec![],
+            overrides: vec![],
+            modules: vec![],
+        }];
+        monomorphize(&mut files);
+        let opts = Options {
+            monomorphize: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            files[0].to_ts(&opts),
+            "// a.rs\n\
+             export interface Users {\n  page: PaginatedUser;\n}\n\
+             export interface PaginatedUser {\n  items: User[];\n  next: CursorUser | null;\n}\n\
+             export interface CursorUser {\n  last: User;\n}\n"
+        );
+    }
+
+    #[test]
+    fn monomorphization_defaults() {
+        let parse = |src| SimpleStruct::new(&syn::parse_str(src).unwrap()).unwrap();
+        let mut files = vec![SimpleFile {
+            name: "a.rs".to_string(),
+            enums: vec![],
+            structs: vec![
+                parse("struct Response<T = ()> { data: T }"),
+                parse("struct Calls { a: Response, b: Response<u8> }"),
+            ],
+            bitflags: vec![],
+            aliases: vec![],
+            consts: vec![],
+            overrides: vec![],
+            modules: vec![],
+        }];
+        monomorphize(&mut files);
+        let opts = Options {
+            monomorphize: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            files[0].to_ts(&opts),
+            "// a.rs\n\
+             export interface Calls {\n  a: Response;\n  b: ResponseU8;\n}\n\
+             export interface ResponseU8 {\n  data: number;\n}\n\
+             export interface Response {\n  data: null;\n}\n"
+        );
+    }
 
-        let syntax = syn::parse_file(&src).expect("Unable to parse file");
+    #[test]
+    fn recursive_types() {
+        let parse = |src| SimpleStruct::new(&syn::parse_str(src).unwrap()).unwrap();
+        let tree = parse("struct Tree { children: Vec<Tree>, parent: Option<Box<Tree>> }");
+        assert_eq!(
+            tree.to_ts(&Options::default()),
+            "export interface Tree {\n  children: Tree[];\n  parent: Tree | null;\n}\n"
+        );
 
-        let mut enums = Vec::new();
-        let mut structs = Vec::new();
+        let files = vec![SimpleFile {
+            name: "a.rs".to_string(),
+            enums: vec![],
+            structs: vec![
+                tree,
+                parse("struct List(Option<Box<List>>);"),
+                parse("struct A(Box<B>);"),
+                parse("struct B(Option<A>);"),
+                parse("struct C(Vec<C>);"),
+            ],
+            bitflags: vec![],
+            aliases: vec![],
+            consts: vec![],
+            overrides: vec![],
+            modules: vec![],
+        }];
+        let mut opts = Options::default();
+        opts.alias_cycles = alias_cycles(&files, &opts);
+        let mut cycles = opts.alias_cycles.iter().collect::<Vec<_>>();
+        cycles.sort();
+        assert_eq!(cycles, vec!["A", "B", "List"]);
+        assert_eq!(
+            files[0].structs[1].to_ts(&opts),
+            "export type List = unknown;\n"
+        );
 
-        for item in syntax.items {
-            if let syn::Item::Enum(s) = item {
-                if let Some(s) = SimpleEnum::from_syn_type(&s) {
-                    enums.push(s);
-                }
-            } else if let syn::Item::Struct(s) = item {
-                if let Some(s) = SimpleStruct::new(&s) {
-                    structs.push(s);
-                }
-            }
-        }
+        let mut files = vec![SimpleFile {
+            name: "a.rs".to_string(),
+            enums: vec![],
+            structs: vec![
+                parse("struct Nested<T> { inner: Option<Box<Nested<Vec<T>>>> }"),
+                parse("struct X { n: Nested<i32> }"),
+            ],
+            bitflags: vec![],
+            aliases: vec![],
+            consts: vec![],
+            overrides: vec![],
+            modules: vec![],
+        }];
+        monomorphize(&mut files);
+        assert_eq!(files[0].structs.len(), 2 + MAX_MONO_DEPTH - 1);
+    }
 
-        SimpleFile {
-            name: path.file_name().unwrap().to_str().unwrap().to_string(),
-            enums: enums,
-            structs: structs,
-        }
+    #[test]
+    fn union_override() {
+        let file: syn::File = syn::parse_str(
+            "#[rsts(type = \"number\")] union Bits { i: u32, f: f32 }
+             union Ignored { a: u8 }",
+        )
+        .unwrap();
+        let f = SimpleFile::from_items(
+            "rsts_union_override.rs".to_string(),
+            file.items,
+            &Options::default(),
+        );
+        assert_eq!(
+            f.to_ts(&Options::default()),
+            "// rsts_union_override.rs\nexport type Bits = number;\n"
+        );
     }
 
-    fn to_ts(&self) -> String {
-        let mut output = format!("// {}\n", self.name);
-        for e in self.enums.iter() {
-            output += &e.to_ts();
-        }
-        for s in self.structs.iter() {
-            output += &s.to_ts();
-        }
-        output
+    #[test]
+    fn type_aliases() {
+        let parse = |src| SimpleAlias::new(&syn::parse_str(src).unwrap()).unwrap();
+        assert_eq!(
+            parse("type Ids = Vec<u64>;").to_ts(&Options::default()),
+            "export type Ids = number[];\n"
+        );
+        assert_eq!(
+            parse("pub type Res<T> = Result<T, ApiError>;").to_ts(&Options::default()),
+            "export type Res<T> = { Ok: T } | { Err: ApiError };\n"
+        );
     }
-}
 
-fn main() {
-    let matches = clap_app!(rsts =>
-        (about: "Convert Rust types to Typescript")
-        (@arg INPUT: +required +multiple "typescript file(s)")
-    )
-    .get_matches();
+    #[test]
+    fn consts() {
+        let parse = |src| {
+            let c: syn::ItemConst = syn::parse_str(src).unwrap();
+            SimpleConst::new(&c.ident, &c.expr).map(|c| c.to_ts())
+        };
+        assert_eq!(
+            parse("pub const MAX_PAGE_SIZE: u32 = 100;"),
+            Some("export const MAX_PAGE_SIZE = 100;\n".to_string())
+        );
+        assert_eq!(
+            parse("const MIN: f64 = -0.5;"),
+            Some("export const MIN = -0.5;\n".to_string())
+        );
+        assert_eq!(
+            parse("const NAME: &str = \"say \\\"hi\\\"\";"),
+            Some("export const NAME = \"say \\\"hi\\\"\";\n".to_string())
+        );
+        assert_eq!(
+            parse("const ON: bool = true;"),
+            Some("export const ON = true;\n".to_string())
+        );
+        assert_eq!(parse("const X: u32 = 1 + 2;"), None);
+    }
 
-    let mut files = Vec::new();
-    for input in matches.values_of("INPUT").unwrap() {
-        files.push(SimpleFile::load(std::path::Path::new(input)));
+    #[test]
+    fn statics() {
+        let file: syn::File = syn::parse_str(
+            "pub static VERSION: &str = \"1.2.0\";
+             static mut COUNTER: u32 = 0;
+             const LIMIT: u32 = 10;",
+        )
+        .unwrap();
+        let opts = Options {
+            include_statics: true,
+            ..Default::default()
+        };
+        let f = SimpleFile::from_items("rsts_statics.rs".to_string(), file.items, &opts);
+        assert_eq!(
+            f.to_ts(&opts),
+            "// rsts_statics.rs\nexport const VERSION = \"1.2.0\";\n"
+        );
     }
 
-    print!("export type DateTimeUtc = string;\n");
-    for f in files {
-        print!("{}", f.to_ts());
+    #[test]
+    fn inline_modules() {
+        let file: syn::File = syn::parse_str(
+            "mod messages {
+                 #[derive(Serialize)] pub struct Request { id: u32 }
+             }
+             #[derive(Serialize)] struct Envelope { req: crate::messages::Request }
+             #[cfg(test)]
+             mod tests {
+                 #[derive(Serialize)] struct Fixture { a: u8 }
+             }",
+        )
+        .unwrap();
+        let mut opts = Options::default();
+        let f = SimpleFile::from_items("rsts_inline_modules.rs".to_string(), file.items, &opts);
+        f.register_interfaces(&mut opts);
+        assert_eq!(
+            f.to_ts(&opts),
+            "// rsts_inline_modules.rs\n\
+             export interface Envelope {\n  req: Request;\n}\n\
+             export interface Request {\n  id: number;\n}\n"
+        );
+        opts.module_namespaces = true;
+        assert_eq!(
+            f.to_ts(&opts),
+            "// rsts_inline_modules.rs\n\
+             export interface Envelope {\n  req: messages.Request;\n}\n\
+             export namespace messages {\n  export interface Request {\n    id: number;\n  }\n}\n"
+        );
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn struct_variant_field_attrs() {
+        let e: syn::ItemEnum = syn::parse_str(
+            "enum E {
+                 Move {
+                     #[serde(rename = \"dx\")] x: i32,
+                     #[serde(skip)] cache: u32,
+                     #[serde(default)] speed: f32,
+                 },
+             }",
+        )
+        .unwrap();
+        let e = SimpleEnum::from_syn_type(&e).unwrap();
+        assert_eq!(
+            e.to_ts(&Options::default()),
+            "export type E =\n  { Move: { dx: number; speed?: number } };\n"
+        );
+        let opts = Options {
+            direction: Direction::Serialize,
+            ..Default::default()
+        };
+        assert_eq!(
+            e.to_ts(&opts),
+            "export type E =\n  { Move: { dx: number; speed: number } };\n"
+        );
+
+        // Same as struct fields: with types and the absent vs null
+        // pattern
+        let e: syn::ItemEnum = syn::parse_str(
+            "enum E {
+                 B {
+                     #[serde(with = \"chrono::serde::ts_seconds\")] at: DateTime<Utc>,
+                     note: Option<Option<String>>,
+                 },
+             }",
+        )
+        .unwrap();
+        let e = SimpleEnum::from_syn_type(&e).unwrap();
+        assert_eq!(
+            e.to_ts(&Options::default()),
+            "export type E =\n  { B: { at: number; note?: string | null } };\n"
+        );
+    }
 
     #[test]
-    fn simple_type_number() {
-        let st = SimpleType::new(vec!["i32".to_string()], vec![]);
-        assert_eq!(st.to_ts(), "number");
+    fn enum_consts() {
+        let e: syn::ItemEnum = syn::parse_str("enum Color { Red = 1, Green, Blue = 10 }").unwrap();
+        let e = SimpleEnum::from_syn_type(&e).unwrap();
+        let opts = Options {
+            enum_consts: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            e.to_ts(&opts),
+            "export type Color =\n  \"Red\" |\n  \"Green\" |\n  \"Blue\";\n\
+             export const Color = {\n  Red: 1,\n  Green: 2,\n  Blue: 10,\n} as const;\n"
+        );
+
+        let e: syn::ItemEnum = syn::parse_str("enum Plain { A, B }").unwrap();
+        let e = SimpleEnum::from_syn_type(&e).unwrap();
+        assert_eq!(e.discriminants_to_ts(), None);
     }
 
     #[test]
-    fn simple_type_string() {
-        let st = SimpleType::new(vec!["String".to_string()], vec![]);
-        assert_eq!(st.to_ts(), "string");
+    fn tuple_struct() {
+        let s: syn::ItemStruct =
+            syn::parse_str("#[derive(Serialize)] struct Point(f64, f64, Option<String>);").unwrap();
+        let s = SimpleStruct::new(&s).unwrap();
+        assert!(s.is_tuple());
+        assert_eq!(
+            s.to_ts(&Options::default()),
+            "export type Point = [number, number, string | null];\n"
+        );
     }
 
     #[test]
-    fn simple_type_option() {
-        let st = SimpleType::new(
-            vec!["Option".to_string()],
-            vec![SimpleType {
-                path: vec!["i32".to_string()],
-                generic_args: vec![],
-            }],
+    fn unit_struct() {
+        let s: syn::ItemStruct = syn::parse_str("#[derive(Serialize)] struct Heartbeat;").unwrap();
+        let s = SimpleStruct::new(&s).unwrap();
+        assert!(!s.is_interface());
+        assert_eq!(
+            s.to_ts(&Options::default()),
+            "export type Heartbeat = null;\n"
         );
+    }
 
-        assert_eq!(st.to_ts(), "number | null");
+    #[test]
+    fn empty_struct() {
+        let s: syn::ItemStruct = syn::parse_str("#[derive(Serialize)] struct Empty {}").unwrap();
+        let s = SimpleStruct::new(&s).unwrap();
+        assert!(!s.is_interface());
+        assert_eq!(
+            s.to_ts(&Options::default()),
+            "export type Empty = Record<string, never>;\n"
+        );
     }
 
     #[test]
-    fn simple_type_vec() {
-        let st = SimpleType::new(
-            vec!["Vec".to_string()],
-            vec![SimpleType {
-                path: vec!["i32".to_string()],
-                generic_args: vec![],
-            }],
+    fn raw_identifiers() {
+        let s: syn::ItemStruct = syn::parse_str(
+            "#[derive(Serialize)] struct r#Match { r#type: String, r#enum: r#Kind }",
+        )
+        .unwrap();
+        let s = SimpleStruct::new(&s).unwrap();
+        assert_eq!(
+            s.to_ts(&Options::default()),
+            "export interface Match {\n  type: string;\n  enum: Kind;\n}\n"
+        );
+        let e: syn::ItemEnum = syn::parse_str("enum E { r#Loop }").unwrap();
+        let e = SimpleEnum::from_syn_type(&e).unwrap();
+        assert_eq!(
+            e.to_ts(&Options::default()),
+            "export type E =\n  \"Loop\";\n"
         );
+    }
 
-        assert_eq!(st.to_ts(), "number[]");
+    #[test]
+    fn quoted_property_names() {
+        let s: syn::ItemStruct = syn::parse_str(
+            "#[derive(Serialize)]
+             #[serde(rename_all = \"kebab-case\")]
+             struct X { content_type: String, #[serde(rename = \"2fa_enabled\")] two_factor: bool, id: u32 }",
+        )
+        .unwrap();
+        let s = SimpleStruct::new(&s).unwrap();
+        assert_eq!(
+            s.to_ts(&Options::default()),
+            "export interface X {\n  \"content-type\": string;\n  \"2fa_enabled\": boolean;\n  \
+             id: number;\n}\n"
+        );
+        let e: syn::ItemEnum = syn::parse_str("#[serde(tag = \"@type\")] enum E { A }").unwrap();
+        let e = SimpleEnum::from_syn_type(&e).unwrap();
+        assert_eq!(
+            e.to_ts(&Options::default()),
+            "export type E =\n  { \"@type\": \"A\" };\n"
+        );
     }
 
     #[test]
-    fn simple_type_vec_option() {
-        let st = SimpleType::new(
-            vec!["Vec".to_string()],
-            vec![SimpleType::new(
-                vec!["Option".to_string()],
-                vec![SimpleType::new(vec!["i32".to_string()], vec![])],
-            )],
+    fn non_exhaustive() {
+        let e: syn::ItemEnum = syn::parse_str("#[non_exhaustive] enum E { A, B(i32) }").unwrap();
+        let e = SimpleEnum::from_syn_type(&e).unwrap();
+        let opts = Options {
+            non_exhaustive_fallback: Some("{ [k: string]: unknown }".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            e.to_ts(&opts),
+            "/** More variants may be added */\nexport type E =\n  \"A\" |\n  { B: number } |\n  \
+             { [k: string]: unknown };\n"
         );
+    }
+
+    #[test]
+    fn excluded_items() {
+        let file: syn::File = syn::parse_str(
+            "#[derive(Serialize)] #[doc(hidden)] struct Internal { a: i32 }
+             #[derive(Serialize)] #[ts_skip] struct Skipped { a: i32 }
+             #[derive(Serialize)] struct Named { a: i32 }
+             #[derive(Serialize)] struct Kept { a: i32 }",
+        )
+        .unwrap();
+        let mut opts = Options::default();
+        opts.exclude_attrs.push("ts_skip".to_string());
+        opts.exclude.insert("Named".to_string());
+        let f = SimpleFile::from_items("a.rs".to_string(), file.items.clone(), &opts);
+        let names: Vec<_> = f.structs.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["Kept"]);
 
-        assert_eq!(st.to_ts(), "(number | null)[]");
+        let opts = Options {
+            include_hidden: true,
+            ..Default::default()
+        };
+        let f = SimpleFile::from_items("a.rs".to_string(), file.items, &opts);
+        assert_eq!(f.structs.len(), 4);
     }
 
     #[test]
-    fn newtype() {
-        let s = SimpleStruct {
-            name: "MyType".to_string(),
-            fields: vec![SimpleField::new(
-                None,
-                SimpleType::new(vec!["String".to_string()], vec![]),
-            )],
+    fn only_pub() {
+        let file: syn::File = syn::parse_str(
+            "#[derive(Serialize)] pub struct A { a: i32 }
+             #[derive(Serialize)] pub(crate) struct B { a: i32 }
+             #[derive(Serialize)] pub(super) struct C { a: i32 }
+             #[derive(Serialize)] struct D { a: i32 }",
+        )
+        .unwrap();
+        let names = |opts: &Options| {
+            let f = SimpleFile::from_items("a.rs".to_string(), file.items.clone(), opts);
+            f.structs.into_iter().map(|s| s.name).collect::<Vec<_>>()
+        };
+        assert_eq!(names(&Options::default()), ["A", "B", "C", "D"]);
+        let opts = Options {
+            only_pub: true,
+            ..Default::default()
         };
+        assert_eq!(names(&opts), ["A"]);
+        let opts = Options {
+            only_pub: true,
+            pub_crate: true,
+            ..Default::default()
+        };
+        assert_eq!(names(&opts), ["A", "B"]);
+    }
 
-        assert_eq!(s.to_ts(), "export type MyType = string;\n")
+    #[test]
+    fn strum_enum() {
+        let e: syn::ItemEnum = syn::parse_str(
+            "#[derive(EnumString, Display)]
+             #[strum(serialize_all = \"snake_case\")]
+             enum SortBy {
+                 CreatedAt,
+                 #[strum(serialize = \"name\", serialize = \"title\")]
+                 Title,
+             }",
+        )
+        .unwrap();
+        let e = SimpleEnum::from_syn_type(&e).unwrap();
+        assert_eq!(
+            e.to_ts(&Options::default()),
+            "export type SortBy =\n  \"created_at\" |\n  /** Also accepted as \"name\" */\n  \"title\";\n"
+        );
+
+        // serde's format wins when it's derived
+        let e: syn::ItemEnum = syn::parse_str(
+            "#[derive(Serialize, EnumString)]
+             #[strum(serialize_all = \"snake_case\")]
+             enum SortBy { CreatedAt }",
+        )
+        .unwrap();
+        let e = SimpleEnum::from_syn_type(&e).unwrap();
+        assert_eq!(
+            e.to_ts(&Options::default()),
+            "export type SortBy =\n  \"CreatedAt\";\n"
+        );
     }
 
     #[test]
-    fn datetime() {
-        let t = SimpleType::new(
-            vec!["DateTime".to_string()],
-            vec![SimpleType::new(vec!["Utc".to_string()], vec![])],
+    fn write_output_atomically() {
+        let dir = std::env::temp_dir().join(format!("rsts-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("types.ts");
+        write_atomic(&path, "export type A = number;\n").unwrap();
+        write_atomic(&path, "export type B = string;\n").unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "export type B = string;\n"
         );
-        assert_eq!(t.to_ts(), "DateTimeUtc");
+        assert!(!dir.join("types.ts.tmp").exists());
+        assert!(write_atomic(&dir.join("missing/types.ts"), "").is_err());
+        fs::remove_dir_all(&dir).unwrap();
     }
 
     #[test]
-    fn hashmap() {
-        let t = SimpleType::new(
-            vec!["HashMap".to_string()],
-            vec![
-                SimpleType::new(vec!["String".to_string()], vec![]),
-                SimpleType::new(vec!["i32".to_string()], vec![]),
-            ],
+    fn per_file_output() {
+        let load = |name: &str, src: &str| {
+            let file: syn::File = syn::parse_str(src).unwrap();
+            SimpleFile::from_items(name.to_string(), file.items, &Options::default())
+        };
+        let files = vec![
+            load(
+                "user.rs",
+                "#[derive(Serialize)] struct User { id: UserId, joined: DateTime<Utc> }
+                 #[derive(Serialize)] struct UserId(u64);",
+            ),
+            load(
+                "post.rs",
+                "#[derive(Serialize)] #[serde(tag = \"User\")] enum Post { Draft { author: User } }",
+            ),
+        ];
+        let module_paths = ["user".to_string(), "post".to_string()];
+        let outputs = split_output(&files, &module_paths, &Options::default()).unwrap();
+        let names: Vec<_> = outputs.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["preamble.ts", "user.ts", "post.ts", "index.ts"]);
+        assert!(outputs[1]
+            .1
+            .starts_with("import type { DateTimeUtc } from \"./preamble\";\n// user.rs\n"));
+        // The tag string isn't an import
+        assert!(outputs[2]
+            .1
+            .starts_with("import type { User } from \"./user\";\n// post.rs\n"));
+
+        // Names defined locally aren't imported, and neither are
+        // property names
+        let files = vec![
+            load(
+                "users.rs",
+                "#[derive(Serialize)] enum Role { Admin }
+                 #[derive(Serialize)] struct User { role: Role }",
+            ),
+            load(
+                "other.rs",
+                "#[derive(Serialize)] enum Role { Guest }
+                 #[derive(Serialize)] #[serde(rename_all = \"PascalCase\")]
+                 struct Member { role: Role, user: String }",
+            ),
+        ];
+        let module_paths = ["api/users".to_string(), "other".to_string()];
+        let outputs = split_output(&files, &module_paths, &Options::default()).unwrap();
+        assert!(outputs[1].1.starts_with("// other.rs\n"));
+
+        // Overrides can name types, and external types mapped to
+        // built-in ones don't need an import
+        let files = vec![
+            load(
+                "user.rs",
+                "#[derive(Serialize)] struct User { id: u32 }
+                 #[derive(Serialize)] struct Path { parts: Vec<String> }",
+            ),
+            load(
+                "doc.rs",
+                "#[derive(Serialize)]
+                 struct Doc { #[rsts(type = \"User\")] owner: u32, p: std::path::Path }",
+            ),
+        ];
+        let module_paths = ["user".to_string(), "doc".to_string()];
+        let outputs = split_output(&files, &module_paths, &Options::default()).unwrap();
+        assert!(outputs[1]
+            .1
+            .starts_with("import type { User } from \"./user\";\n// doc.rs\n"));
+
+        let dup = vec![load("a.rs", ""), load("a.rs", "")];
+        let module_paths = ["a".to_string(), "a".to_string()];
+        assert!(split_output(&dup, &module_paths, &Options::default()).is_err());
+    }
+
+    #[test]
+    fn module_tree_output() {
+        use std::path::Path;
+        let root = Some(Path::new("src"));
+        assert_eq!(
+            module_path(Path::new("src/api/users.rs"), root).unwrap(),
+            "api/users"
+        );
+        assert_eq!(
+            module_path(Path::new("src/api/mod.rs"), root).unwrap(),
+            "api"
         );
-        assert_eq!(t.to_ts(), "Record<string, number>");
+        assert_eq!(module_path(Path::new("src/lib.rs"), root).unwrap(), "lib");
+        assert_eq!(
+            module_path(Path::new("src/api/users.rs"), None).unwrap(),
+            "users"
+        );
+        assert!(module_path(Path::new("other/users.rs"), root).is_err());
+
+        assert_eq!(relative_module("api/users", "api/posts"), "./posts");
+        assert_eq!(relative_module("api/users", "preamble"), "../preamble");
+        assert_eq!(relative_module("lib", "api/users"), "./api/users");
+        assert_eq!(relative_module("api/v1/users", "api/posts"), "../posts");
+        assert_eq!(relative_module("api/users", "api"), "../api");
     }
 
     #[test]
-    fn enum_to_ts() {
-        let e = SimpleEnum {
-            name: "myEnum".to_string(),
-            variants: vec![SimpleVariant::new("myVariant".to_string(), vec![])],
+    fn barrel_index() {
+        let modules = vec![
+            ("preamble".to_string(), "export type DateTimeUtc = string;\n".to_string()),
+            (
+                "api/color".to_string(),
+                "export type Color =\n  1 |\n  2;\nexport const Color = {\n  Red: 1,\n} as const;\n\
+                 export interface Paint {\n  c: Color;\n}\n"
+                    .to_string(),
+            ),
+            ("other".to_string(), "export type Paint = string;\n".to_string()),
+        ];
+        let mut symbols = HashMap::new();
+        for (module, ts) in modules.iter() {
+            for (_, name) in exported_names(ts) {
+                symbols.entry(name).or_insert(module.as_str());
+            }
+        }
+        assert_eq!(
+            barrel(&modules, &symbols),
+            "export type { DateTimeUtc } from \"./preamble\";\n\
+             export type { Paint } from \"./api/color\";\n\
+             export { Color } from \"./api/color\";\n"
+        );
+    }
+
+    #[test]
+    fn declarations() {
+        let ts = "// a.rs\n\
+                  export const MAX = 100;\n\
+                  export const Color = {\n  Red: 1,\n} as const;\n\
+                  /** Doc */\n\
+                  export interface A {\n  a: number;\n}\n\
+                  export namespace m {\n  export type B = string;\n  \
+                  export const Flags = {\n    X: 1,\n  } as const;\n}\n";
+        assert_eq!(
+            to_declarations(ts),
+            "// a.rs\n\
+             declare const MAX: 100;\n\
+             declare const Color: {\n  readonly Red: 1;\n};\n\
+             /** Doc */\n\
+             interface A {\n  a: number;\n}\n\
+             declare namespace m {\n  export type B = string;\n  \
+             export const Flags: {\n    readonly X: 1;\n  };\n}\n"
+        );
+    }
+
+    #[test]
+    fn output_namespace() {
+        let ts =
+            "export type DateTimeUtc = string;\n// a.rs\nexport interface A {\n  a: number;\n}\n";
+        let wrapped = namespace("Api", ts);
+        assert_eq!(
+            wrapped,
+            "export namespace Api {\n  export type DateTimeUtc = string;\n  // a.rs\n  \
+             export interface A {\n    a: number;\n  }\n}\n"
+        );
+        assert!(to_declarations(&wrapped).starts_with("declare namespace Api {\n  export type"));
+    }
+
+    #[test]
+    fn output_declare_module() {
+        let ts = "export const MAX = 1;\nexport interface A {\n  a: number;\n}\n";
+        assert_eq!(
+            to_declarations(&declare_module("my-api-types", ts)),
+            "declare module \"my-api-types\" {\n  export const MAX: 1;\n  \
+             export interface A {\n    a: number;\n  }\n}\n"
+        );
+    }
+
+    #[test]
+    fn generated_header() {
+        let inputs = vec![
+            ("a.rs".to_string(), "a".to_string()),
+            ("b.rs".to_string(), "bc".to_string()),
+        ];
+        assert_eq!(
+            header(DEFAULT_HEADER, &inputs),
+            format!(
+                "// Generated by rsts v{} from a.rs, b.rs (sha256: \
+                 751a22ef906b6ec5386b9f3b9e649709549710708b9656b137de1a11672af7b7) — do not edit\n",
+                env!("CARGO_PKG_VERSION")
+            )
+        );
+        assert_eq!(header("// {inputs}\n", &inputs[..1]), "// a.rs\n");
+
+        // Moving text between inputs or renaming them changes the hash
+        let hash = |inputs: &[(&str, &str)]| {
+            let inputs: Vec<_> = inputs
+                .iter()
+                .map(|(name, contents)| (name.to_string(), contents.to_string()))
+                .collect();
+            header("{sha256}", &inputs)
         };
-        assert_eq!(e.to_ts(), "export type myEnum =\n  \"myVariant\";\n");
+        let original = hash(&[("a.rs", "a"), ("b.rs", "bc")]);
+        assert_ne!(original, hash(&[("a.rs", "ab"), ("b.rs", "c")]));
+        assert_ne!(original, hash(&[("b.rs", "bc"), ("a.rs", "a")]));
+        assert_ne!(original, hash(&[("c.rs", "a"), ("b.rs", "bc")]));
     }
 
     #[test]
-    fn test_attr_to_derives() {
-        let s: syn::ItemStruct = syn::parse_str("#[derive(A, B)] struct X {}").unwrap();
+    fn snippet_files() {
+        let path = std::env::temp_dir().join(format!("rsts-snippet-{}.ts", std::process::id()));
+        fs::write(&path, "export type JsonValue = unknown;").unwrap();
         assert_eq!(
-            attr_to_derives(&s.attrs[0]),
-            vec!["A".to_string(), "B".to_string()]
+            read_snippet(path.to_str()),
+            "export type JsonValue = unknown;\n"
+        );
+        fs::remove_file(&path).unwrap();
+        assert_eq!(read_snippet(None), "");
+
+        // Snippets aren't converted to declarations
+        let snippet = "export function isUser(x: unknown): x is User { return true; }\n";
+        assert_eq!(
+            wrap_output(
+                "export type User = string;\n",
+                (snippet, ""),
+                None,
+                None,
+                true
+            ),
+            format!("{}type User = string;\n", snippet)
+        );
+        assert_eq!(
+            wrap_output(
+                "export type A = B;\n",
+                ("", snippet),
+                Some("api"),
+                None,
+                false
+            ),
+            format!(
+                "export namespace api {{\n  export type A = B;\n}}\n{}",
+                snippet
+            )
+        );
+    }
+
+    #[test]
+    fn datetime_alias_when_used() {
+        let opts = Options::default();
+        assert_eq!(
+            preamble(&opts, "export interface A {\n  a: number;\n}\n"),
+            ""
+        );
+        assert_eq!(
+            preamble(&opts, "export interface A {\n  a: DateTimeUtc;\n}\n"),
+            "export type DateTimeUtc = string;\n"
+        );
+
+        let opts = Options {
+            datetime_alias: ("Instant".to_string(), "Date".to_string()),
+            ..Default::default()
+        };
+        let ty = SimpleType::from_syn_type(&syn::parse_str("DateTime<Utc>").unwrap()).unwrap();
+        let ts = format!("export type A = {};\n", ty.to_ts(&opts));
+        assert_eq!(preamble(&opts, &ts), "export type Instant = Date;\n");
+    }
+
+    #[test]
+    fn doc_comments() {
+        let file: syn::File = syn::parse_str(
+            "/// A registered user.
+             ///
+             /// Created on signup.
+             #[derive(Serialize, Deserialize)]
+             #[serde(deny_unknown_fields)]
+             struct User {
+                 /// Display name, see */
+                 #[serde(alias = \"nick\")]
+                 name: String,
+                 age: u8,
+             }
+             /**
+              * Account state
+              */
+             #[derive(Serialize)]
+             enum State {
+                 /// Not yet verified
+                 Pending,
+                 Active,
+             }",
+        )
+        .unwrap();
+        let f = SimpleFile::from_items("a.rs".to_string(), file.items, &Options::default());
+        assert_eq!(
+            f.structs[0].to_ts(&Options::default()),
+            "/**\n * A registered user.\n *\n * Created on signup.\n *\n \
+             * Unknown fields are rejected\n */\nexport interface User {\n  \
+             /**\n   * Display name, see *\\/\n   *\n   * Also accepted as \"nick\"\n   */\n  \
+             name: string;\n  age: number;\n}\n"
+        );
+        assert_eq!(
+            f.enums[0].to_ts(&Options::default()),
+            "/** Account state */\nexport type State =\n  /** Not yet verified */\n  \"Pending\" |\n  \"Active\";\n"
+        );
+    }
+
+    #[test]
+    fn defined_types_take_precedence() {
+        let file: syn::File = syn::parse_str(
+            "#[derive(Serialize)] struct Path { segments: Vec<String> }
+             #[derive(Serialize)] struct Route { path: Path, file: std::path::Path }",
+        )
+        .unwrap();
+        let f = SimpleFile::from_items("a.rs".to_string(), file.items, &Options::default());
+        let mut opts = Options::default();
+        f.register_interfaces(&mut opts);
+        assert_eq!(
+            f.structs[1].to_ts(&opts),
+            "export interface Route {\n  path: Path;\n  file: string;\n}\n"
+        );
+    }
+
+    #[test]
+    fn int64_uses() {
+        let file: syn::File = syn::parse_str(
+            "#[derive(Serialize)] struct A { a: u32, b: Option<Vec<u64>> }
+             #[derive(Serialize)] struct B { #[rsts(type = \"string\")] id: i64 }
+             enum C { X(NonZeroI64) }",
+        )
+        .unwrap();
+        let f = SimpleFile::from_items("a.rs".to_string(), file.items, &Options::default());
+        let uses: Vec<_> = f.types().iter().map(|ty| ty.uses_int64()).collect();
+        assert_eq!(uses, [false, true, true]);
+    }
+    #[test]
+    fn discriminant_overflow() {
+        let e: syn::ItemEnum = syn::parse_str(
+            "#[derive(Serialize_repr)] #[repr(i64)] enum Big { A = 9223372036854775807 }",
+        )
+        .unwrap();
+        let e = SimpleEnum::from_syn_type(&e).unwrap();
+        let opts = Options {
+            enum_consts: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            e.to_ts(&opts),
+            "export type Big =\n  9223372036854775807;\n\
+             export const Big = {\n  A: 9223372036854775807,\n} as const;\n"
         );
+
+        let e: syn::ItemEnum = syn::parse_str(
+            "#[derive(Serialize_repr)] #[repr(i64)] enum Small { A = -9223372036854775808 }",
+        )
+        .unwrap();
+        let e = SimpleEnum::from_syn_type(&e).unwrap();
+        assert_eq!(e.variants[0].discriminant, Some(i64::MIN));
+
+        // The implicit value after i64::MAX doesn't exist
+        let e: syn::ItemEnum = syn::parse_str(
+            "#[derive(Serialize_repr)] #[repr(i64)] enum Wrap { A = 9223372036854775807, B }",
+        )
+        .unwrap();
+        let e = SimpleEnum::from_syn_type(&e).unwrap();
+        assert_eq!(e.discriminants(), None);
+        assert_eq!(e.to_ts(&opts), "export type Wrap =\n  number;\n");
+    }
+    #[test]
+    fn monomorphize_aliases() {
+        let parse = |src| SimpleStruct::new(&syn::parse_str(src).unwrap()).unwrap();
+        let alias = |src| SimpleAlias::new(&syn::parse_str(src).unwrap()).unwrap();
+        let mut files = vec![SimpleFile {
+            name: "a.rs".to_string(),
+            enums: vec![],
+            structs: vec![
+                parse("struct Paginated<T> { items: Vec<T> }"),
+                parse("struct Reply { r: Res<u8> }"),
+            ],
+            bitflags: vec![],
+            aliases: vec![
+                alias("type UserPage = Paginated<User>;"),
+                alias("type Res<T> = Result<T, String>;"),
+            ],
+            consts: vec![],
+            overrides: vec![],
+            modules: vec![],
+        }];
+        monomorphize(&mut files);
+        let opts = Options {
+            monomorphize: true,
+            ..Default::default()
+        };
+        let ts = files[0].to_ts(&opts);
+        assert!(ts.contains("export type UserPage = PaginatedUser;\n"));
+        assert!(ts.contains("export interface PaginatedUser {\n  items: User[];\n}\n"));
+        assert!(ts.contains("export interface Reply {\n  r: ResU8;\n}\n"));
+        assert!(ts.contains("export type ResU8 = "));
+        assert!(!ts.contains("Res<T>"));
+        assert!(!ts.contains("Paginated<T>"));
     }
 }